@@ -0,0 +1,210 @@
+//! A headless mpv player to write integration tests for mpv-client plugins,
+//! without a desktop session.
+//!
+//! The player runs with `vo=null` and `ao=null`, ignores the user's
+//! configuration, and plays synthetic media from lavfi sources:
+//!
+//! ```ignore
+//! use mpv_client_test::{testsrc, TestPlayer};
+//! use std::time::Duration;
+//!
+//! let mut player = TestPlayer::new()?;
+//! player.load(&testsrc(Duration::from_secs(5)))?;
+//! player.keypress("SPACE")?;
+//! player.wait_until_property("pause", true, Duration::from_secs(1))?;
+//! ```
+
+use mpv_client::{Client, Event, EventKind, Format, Handle};
+
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
+
+/// Default timeout of `TestPlayer::load`.
+const LOAD_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest single wait while polling a property.
+const POLL_INTERVAL: f64 = 0.05;
+
+/// A headless player, see the crate documentation. It terminates the player
+/// when dropped.
+pub struct TestPlayer {
+    client: Option<Client>,
+}
+
+#[derive(Debug)]
+pub enum TestError {
+    /// A libmpv call failed.
+    Mpv(mpv_client::Error),
+    /// The player shut down while waiting.
+    Shutdown,
+    /// The condition wasn't met before the timeout.
+    Timeout(String),
+}
+
+pub type Result<T> = std::result::Result<T, TestError>;
+
+impl fmt::Display for TestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Mpv(e) => write!(f, "mpv error: {}", e),
+            Self::Shutdown => f.write_str("the player shut down"),
+            Self::Timeout(what) => write!(f, "timed out waiting for {}", what),
+        }
+    }
+}
+
+impl std::error::Error for TestError {}
+
+impl From<mpv_client::Error> for TestError {
+    fn from(e: mpv_client::Error) -> Self {
+        Self::Mpv(e)
+    }
+}
+
+/// A video test pattern of `duration`, 320x240 at 25 fps.
+pub fn testsrc(duration: Duration) -> String {
+    format!(
+        "av://lavfi:testsrc=duration={}:size=320x240:rate=25",
+        duration.as_secs_f64()
+    )
+}
+
+/// A 440 Hz sine wave of `duration`.
+pub fn sine(duration: Duration) -> String {
+    format!("av://lavfi:sine=frequency=440:duration={}", duration.as_secs_f64())
+}
+
+impl TestPlayer {
+    /// Create and initialize a headless player.
+    pub fn new() -> Result<Self> {
+        Self::with_options(&[])
+    }
+
+    /// Same as `TestPlayer::new`, with extra options set before the player is
+    /// initialized, e.g. `("keep-open", "yes")`.
+    pub fn with_options(options: &[(&str, &str)]) -> Result<Self> {
+        let mut client = Client::new()?;
+        let defaults = [
+            ("config", "no"),
+            ("vo", "null"),
+            ("ao", "null"),
+            ("idle", "yes"),
+            ("terminal", "no"),
+            ("input-default-bindings", "yes"),
+        ];
+        for (name, value) in defaults.iter().chain(options) {
+            client.set_property(name, value.to_string())?;
+        }
+        Ok(Self {
+            client: Some(client.initialize()?),
+        })
+    }
+
+    fn client(&mut self) -> &mut Client {
+        self.client.as_mut().expect("the player is running")
+    }
+
+    /// Load `url` (see `testsrc` and `sine`) and wait until it is loaded.
+    pub fn load(&mut self, url: &str) -> Result<()> {
+        self.command(["loadfile", url, "replace"])?;
+        self.wait_for(EventKind::FileLoaded, LOAD_TIMEOUT).map(|_| ())
+    }
+
+    /// Wait for the next event matching `predicate`, and return it. Other
+    /// events are discarded. Callbacks registered on the handle (timers, key
+    /// bindings...) run while waiting.
+    ///
+    /// The returned event refers to memory owned by mpv, which is only valid
+    /// until the next wait.
+    pub fn wait_for_event<F>(&mut self, timeout: Duration, mut predicate: F) -> Result<Event>
+    where
+        F: FnMut(&Event) -> bool,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(TestError::Timeout(String::from("event")));
+            }
+            match self.wait_event(remaining.as_secs_f64()) {
+                Event::Shutdown => return Err(TestError::Shutdown),
+                event if predicate(&event) => return Ok(event),
+                _ => {}
+            }
+        }
+    }
+
+    /// Wait for the next event of the given kind.
+    pub fn wait_for(&mut self, kind: EventKind, timeout: Duration) -> Result<Event> {
+        self.wait_for_event(timeout, |event| EventKind::of(event) == Some(kind))
+            .map_err(|e| match e {
+                TestError::Timeout(_) => TestError::Timeout(format!("event '{}'", kind)),
+                e => e,
+            })
+    }
+
+    /// Wait until the property `name` equals `expected`, while processing
+    /// events.
+    pub fn wait_until_property<T>(&mut self, name: &str, expected: T, timeout: Duration) -> Result<()>
+    where
+        T: Format + PartialEq + fmt::Debug,
+    {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let value = self.get_property::<T>(name).ok();
+            if value.as_ref() == Some(&expected) {
+                return Ok(());
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(TestError::Timeout(format!(
+                    "property '{}' to be {:?}, it is {:?}",
+                    name, expected, value
+                )));
+            }
+            if let Event::Shutdown = self.wait_event(remaining.as_secs_f64().min(POLL_INTERVAL)) {
+                return Err(TestError::Shutdown);
+            }
+        }
+    }
+
+    /// Simulate pressing and releasing `key` (in input.conf syntax) with the
+    /// `keypress` command. The bindings are run asynchronously, process events
+    /// to see their effect.
+    pub fn keypress(&mut self, key: &str) -> Result<()> {
+        Ok(self.command(["keypress", key])?)
+    }
+
+    /// Simulate pressing `key` down, until `TestPlayer::keyup`.
+    pub fn keydown(&mut self, key: &str) -> Result<()> {
+        Ok(self.command(["keydown", key])?)
+    }
+
+    /// Simulate releasing `key`.
+    pub fn keyup(&mut self, key: &str) -> Result<()> {
+        Ok(self.command(["keyup", key])?)
+    }
+}
+
+impl Deref for TestPlayer {
+    type Target = Handle;
+
+    fn deref(&self) -> &Self::Target {
+        self.client.as_ref().expect("the player is running")
+    }
+}
+
+impl DerefMut for TestPlayer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.client()
+    }
+}
+
+impl Drop for TestPlayer {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            client.terminate();
+        }
+    }
+}
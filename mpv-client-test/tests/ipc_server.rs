@@ -0,0 +1,98 @@
+use mpv_client::ipc::{IpcClient, IpcError, IpcServer};
+use mpv_client::{Event, Node};
+use mpv_client_test::TestPlayer;
+
+use serde_json::json;
+use std::path::PathBuf;
+
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("mpv-client-test-{}-{}.sock", std::process::id(), name))
+}
+
+#[test]
+fn serves_properties() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = IpcServer::new(socket_path("properties")).start(&mut player).unwrap();
+    let mut client = IpcClient::connect(listener.path()).unwrap();
+
+    assert!(client.get_property::<bool>("idle-active").unwrap());
+    client.set_property("pause", true).unwrap();
+    assert!(client.get_property::<bool>("pause").unwrap());
+    assert!(matches!(
+        client.get_property::<String>("no-such-property"),
+        Err(IpcError::Mpv(_))
+    ));
+}
+
+#[test]
+fn sends_property_changes() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = IpcServer::new(socket_path("observe")).start(&mut player).unwrap();
+    let mut client = IpcClient::connect(listener.path()).unwrap();
+
+    client.observe_property::<i64>(1, "volume").unwrap();
+    player.set_property("volume", 42i64).unwrap();
+    loop {
+        match client.wait_event(5.) {
+            Event::PropertyChange(1, property) if property.data::<i64>() == Some(42) => break,
+            Event::None | Event::Shutdown => panic!("no change of volume"),
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn runs_custom_methods() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = IpcServer::new(socket_path("methods"))
+        .method("echo", |_, params| Ok(params))
+        .method("volume-percent", |handle, _| {
+            let volume = handle.get_property::<f64>("volume").map_err(|e| e.to_string())?;
+            Ok(json!(format!("{}%", volume)))
+        })
+        .start(&mut player)
+        .unwrap();
+    let mut client = IpcClient::connect(listener.path()).unwrap();
+
+    let echo = client.command_ret(["echo", "a", "b"]).unwrap();
+    assert_eq!(
+        echo,
+        Node::Array(vec![Node::String(String::from("a")), Node::String(String::from("b"))])
+    );
+    client.command(["set", "volume", "50"]).unwrap();
+    assert_eq!(
+        client.command_ret(["volume-percent"]).unwrap(),
+        Node::String(String::from("50%"))
+    );
+}
+
+#[test]
+fn rejects_connections() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = IpcServer::new(socket_path("reject"))
+        .accept(|_| false)
+        .start(&mut player)
+        .unwrap();
+    let mut client = IpcClient::connect(listener.path()).unwrap();
+
+    assert!(client.command(["stop"]).is_err());
+}
+
+#[test]
+fn stops_when_player_shuts_down() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = IpcServer::new(socket_path("shutdown")).start(&mut player).unwrap();
+    let mut client = IpcClient::connect(listener.path()).unwrap();
+    client.command(["stop"]).unwrap();
+
+    // Terminating the player waits for the clients of the server.
+    drop(player);
+    loop {
+        match client.wait_event(5.) {
+            Event::Shutdown => break,
+            Event::None => panic!("no shutdown"),
+            _ => {}
+        }
+    }
+    drop(listener);
+}
@@ -0,0 +1,153 @@
+use mpv_client::mpris::Mpris;
+use mpv_client_test::{testsrc, TestPlayer};
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{ObjectPath, OwnedValue};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const BUS_NAME: &str = "org.mpris.MediaPlayer2.test";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A private `dbus-daemon`, killed when dropped.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    fn start() -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is installed");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        Self {
+            daemon,
+            address: address.trim().to_owned(),
+        }
+    }
+
+    fn connect(&self) -> Connection {
+        zbus::blocking::connection::Builder::address(self.address.as_str())
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+fn player_proxy(connection: &Connection) -> Proxy<'_> {
+    Proxy::new(connection, BUS_NAME, OBJECT_PATH, PLAYER_INTERFACE).unwrap()
+}
+
+/// Poll `property` of the player interface until it satisfies `predicate`.
+fn wait_until<T, F>(proxy: &Proxy, property: &str, predicate: F) -> T
+where
+    T: TryFrom<OwnedValue> + std::fmt::Debug,
+    T::Error: Into<zbus::Error>,
+    F: Fn(&T) -> bool,
+{
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        let value = proxy.get_property::<T>(property).unwrap();
+        if predicate(&value) {
+            return value;
+        }
+        if Instant::now() > deadline {
+            panic!("unexpected {} {:?}", property, value);
+        }
+        thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn publishes_player_state() {
+    let bus = Bus::start();
+    let mut player = TestPlayer::new().unwrap();
+    let _service = Mpris::new()
+        .name("test")
+        .address(&bus.address)
+        .start(&mut player)
+        .unwrap();
+    let connection = bus.connect();
+    let proxy = player_proxy(&connection);
+
+    wait_until::<String, _>(&proxy, "PlaybackStatus", |status| status == "Stopped");
+    wait_until::<f64, _>(&proxy, "Volume", |volume| *volume == 1.);
+
+    player.load(&testsrc(Duration::from_secs(10))).unwrap();
+    wait_until::<String, _>(&proxy, "PlaybackStatus", |status| status == "Playing");
+    let metadata = wait_until::<HashMap<String, OwnedValue>, _>(&proxy, "Metadata", |metadata| {
+        metadata.contains_key("mpris:length")
+    });
+    let track_id = ObjectPath::try_from(metadata["mpris:trackid"].try_clone().unwrap()).unwrap();
+    assert_eq!(track_id.as_str(), "/io/mpv/Track/0");
+    // The length is read from the file, which can be off by a frame.
+    let length = i64::try_from(&metadata["mpris:length"]).unwrap();
+    assert!((length - 10_000_000).abs() < 100_000, "length {}", length);
+}
+
+#[test]
+fn translates_method_calls() {
+    let bus = Bus::start();
+    let mut player = TestPlayer::new().unwrap();
+    let _service = Mpris::new()
+        .name("test")
+        .address(&bus.address)
+        .start(&mut player)
+        .unwrap();
+    let connection = bus.connect();
+    let proxy = player_proxy(&connection);
+
+    player.load(&testsrc(Duration::from_secs(10))).unwrap();
+    proxy.call_method("Pause", &()).unwrap();
+    player.wait_until_property("pause", true, TIMEOUT).unwrap();
+    proxy.call_method("PlayPause", &()).unwrap();
+    player.wait_until_property("pause", false, TIMEOUT).unwrap();
+
+    proxy.set_property("Volume", 0.5).unwrap();
+    player.wait_until_property("volume", 50., TIMEOUT).unwrap();
+    proxy.set_property("LoopStatus", "Track").unwrap();
+    player
+        .wait_until_property("loop-file", String::from("inf"), TIMEOUT)
+        .unwrap();
+    wait_until::<String, _>(&proxy, "LoopStatus", |status| status == "Track");
+
+    proxy.call_method("Stop", &()).unwrap();
+    player.wait_until_property("idle-active", true, TIMEOUT).unwrap();
+}
+
+#[test]
+fn releases_player_on_shutdown() {
+    let bus = Bus::start();
+    let mut player = TestPlayer::new().unwrap();
+    let _service = Mpris::new()
+        .name("test")
+        .address(&bus.address)
+        .start(&mut player)
+        .unwrap();
+    let connection = bus.connect();
+    let proxy = player_proxy(&connection);
+    wait_until::<String, _>(&proxy, "PlaybackStatus", |status| status == "Stopped");
+
+    // Terminating the player waits for the clients of the service.
+    drop(player);
+    assert!(proxy.call_method("Pause", &()).is_err());
+}
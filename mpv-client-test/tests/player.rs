@@ -0,0 +1,126 @@
+use mpv_client::{Event, EventKind, KeyBindingFlags};
+use mpv_client_test::{sine, testsrc, TestError, TestPlayer};
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[test]
+fn loads_synthetic_video() {
+    let mut player = TestPlayer::new().unwrap();
+    player.load(&testsrc(Duration::from_secs(2))).unwrap();
+
+    assert_eq!(player.get_property::<i64>("width").unwrap(), 320);
+    assert_eq!(player.get_property::<i64>("height").unwrap(), 240);
+}
+
+#[test]
+fn loads_synthetic_audio() {
+    let mut player = TestPlayer::new().unwrap();
+    player.load(&sine(Duration::from_secs(2))).unwrap();
+
+    player.wait_until_property("idle-active", false, TIMEOUT).unwrap();
+    assert!(player.get_property::<String>("audio-codec-name").is_ok());
+}
+
+#[test]
+fn waits_for_end_of_file() {
+    let mut player = TestPlayer::new().unwrap();
+    player.load(&testsrc(Duration::from_millis(500))).unwrap();
+
+    player.wait_for(EventKind::EndFile, TIMEOUT).unwrap();
+    player.wait_until_property("idle-active", true, TIMEOUT).unwrap();
+}
+
+#[test]
+fn times_out_on_property() {
+    let mut player = TestPlayer::new().unwrap();
+
+    let result = player.wait_until_property("pause", true, Duration::from_millis(200));
+    assert!(matches!(result, Err(TestError::Timeout(_))));
+}
+
+#[test]
+fn keypress_runs_default_bindings() {
+    let mut player = TestPlayer::new().unwrap();
+    player.load(&testsrc(Duration::from_secs(10))).unwrap();
+
+    player.keypress("SPACE").unwrap();
+    player.wait_until_property("pause", true, TIMEOUT).unwrap();
+}
+
+#[test]
+fn keypress_runs_key_binding_callbacks() {
+    let mut player = TestPlayer::new().unwrap();
+    let pressed = Arc::new(AtomicBool::new(false));
+
+    let flag = pressed.clone();
+    player
+        .add_key_binding(Some("Ctrl+t"), "test", KeyBindingFlags::default(), move |_, _| {
+            flag.store(true, Ordering::SeqCst)
+        })
+        .unwrap();
+    player.keypress("Ctrl+t").unwrap();
+
+    // The callback runs while waiting for events, and consumes its event.
+    let deadline = Instant::now() + TIMEOUT;
+    while !pressed.load(Ordering::SeqCst) && Instant::now() < deadline {
+        player.wait_event(0.1);
+    }
+    assert!(pressed.load(Ordering::SeqCst), "key binding callback not called");
+}
+
+#[test]
+fn rounds_up_zero_timer_period() {
+    let mut player = TestPlayer::new().unwrap();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let count = calls.clone();
+    let timer = player.add_periodic_timer(Duration::ZERO, move |_| {
+        count.fetch_add(1, Ordering::SeqCst);
+    });
+    assert_eq!(timer.timeout(), Duration::from_millis(1));
+
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(200) {
+        player.wait_event(0.05);
+    }
+    let calls = calls.load(Ordering::SeqCst);
+    // At most once per millisecond, give or take the last wait.
+    assert!(calls > 0 && calls <= 260, "{} calls", calls);
+}
+
+#[test]
+fn runs_property_callbacks_after_queue_overflow() {
+    let mut player = TestPlayer::new().unwrap();
+    let changes = Arc::new(AtomicUsize::new(0));
+
+    let counter = changes.clone();
+    let reply = player
+        .observe_property_callback::<i64, _>("volume", move |_, _| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+    let deadline = Instant::now() + TIMEOUT;
+    while changes.load(Ordering::SeqCst) == 0 && Instant::now() < deadline {
+        player.wait_event(0.1);
+    }
+    assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+    // More messages than the event queue holds
+    let name = player.name().to_owned();
+    for _ in 0..2000 {
+        player.command(["script-message-to", &name, "flood"]).unwrap();
+    }
+    let mut overflowed = false;
+    while !(overflowed && changes.load(Ordering::SeqCst) > 1) {
+        assert!(Instant::now() < deadline + TIMEOUT, "no resynchronization");
+        match player.wait_event(0.1) {
+            Event::QueueOverflow => overflowed = true,
+            Event::PropertyChange(id, _) => assert_ne!(id, reply, "property change not dispatched"),
+            _ => {}
+        }
+    }
+}
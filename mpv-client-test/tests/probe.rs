@@ -0,0 +1,56 @@
+use mpv_client::probe::{probe, ProbePool, TrackType};
+use mpv_client_test::{sine, testsrc};
+
+use std::time::Duration;
+
+/// Check a duration read from the file, which can be off by a frame.
+fn assert_duration(duration: Option<f64>, expected: f64) {
+    let duration = duration.expect("no duration");
+    assert!(
+        (duration - expected).abs() < 0.1,
+        "duration {} instead of {}",
+        duration,
+        expected
+    );
+}
+
+#[test]
+fn probes_synthetic_video() {
+    let info = probe(&testsrc(Duration::from_secs(3))).unwrap();
+
+    assert_duration(info.duration, 3.);
+    let video: Vec<_> = info.tracks(TrackType::Video).collect();
+    assert_eq!(video.len(), 1);
+    assert_eq!((video[0].width, video[0].height), (Some(320), Some(240)));
+    assert!(info.tracks(TrackType::Audio).next().is_none());
+}
+
+#[test]
+fn fails_on_missing_file() {
+    assert!(probe("/nonexistent/file.mkv").is_err());
+}
+
+#[test]
+fn probes_batches_in_order() {
+    let pool = ProbePool::new(2);
+    let paths = [
+        testsrc(Duration::from_secs(1)),
+        sine(Duration::from_secs(2)),
+        String::from("/nonexistent/file.mkv"),
+        testsrc(Duration::from_secs(4)),
+    ];
+
+    let results = pool.probe_all(paths.clone());
+
+    assert_eq!(results.len(), paths.len());
+    for ((path, result), expected) in results.iter().zip(&paths) {
+        assert_eq!(path, expected);
+        if let Ok(info) = result {
+            assert_eq!(&info.path, path);
+        }
+    }
+    assert_duration(results[0].1.as_ref().unwrap().duration, 1.);
+    assert!(results[1].1.as_ref().unwrap().tracks(TrackType::Audio).next().is_some());
+    assert!(results[2].1.is_err());
+    assert_duration(results[3].1.as_ref().unwrap().duration, 4.);
+}
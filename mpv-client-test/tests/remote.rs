@@ -0,0 +1,210 @@
+use mpv_client::remote::{RemoteListener, RemoteServer};
+use mpv_client_test::TestPlayer;
+
+use serde_json::{json, Value};
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+use tungstenite::client::IntoClientRequest;
+use tungstenite::http::HeaderValue;
+use tungstenite::{Message, WebSocket};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Send an HTTP request to the server, and return the status code and the
+/// body of the response. The `Host` header is the address of the server,
+/// unless given in `headers`.
+fn request(listener: &RemoteListener, method: &str, path: &str, headers: &[(&str, &str)], body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(listener.address()).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nContent-Length: {}\r\n",
+        method,
+        path,
+        body.len()
+    )
+    .unwrap();
+    if !headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("host")) {
+        write!(stream, "Host: {}\r\n", listener.address()).unwrap();
+    }
+    for (name, value) in headers {
+        write!(stream, "{}: {}\r\n", name, value).unwrap();
+    }
+    write!(stream, "\r\n{}", body).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    (status, body.to_owned())
+}
+
+fn json_request(listener: &RemoteListener, method: &str, path: &str, body: &Value) -> Value {
+    let (status, body) = request(listener, method, path, &[], &body.to_string());
+    assert_eq!(status, 200, "{}", body);
+    serde_json::from_str(&body).unwrap()
+}
+
+/// Read the next text message of a WebSocket as JSON.
+fn read_json<S: Read + Write>(socket: &mut WebSocket<S>) -> Value {
+    loop {
+        if let Message::Text(text) = socket.read().unwrap() {
+            return serde_json::from_str(&text).unwrap();
+        }
+    }
+}
+
+#[test]
+fn serves_state() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    let deadline = Instant::now() + TIMEOUT;
+    loop {
+        let state = json_request(&listener, "GET", "/api/state", &Value::Null);
+        if state["idle-active"] == json!(true) {
+            break;
+        }
+        assert!(Instant::now() < deadline, "no state: {}", state);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn runs_allowed_commands() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    let reply = json_request(&listener, "POST", "/api/command", &json!(["set", "volume", "30"]));
+    assert_eq!(reply["error"], "success");
+    assert_eq!(player.get_property::<i64>("volume").unwrap(), 30);
+
+    for command in [
+        json!(["run", "true"]),
+        json!({ "name": "subprocess", "args": ["true"] }),
+        json!(["set", "input-ipc-server", "/tmp/mpv-client-test-remote.sock"]),
+        json!(["cycle-values", "!reverse", "stream-record", "/tmp/x", ""]),
+    ] {
+        let reply = json_request(&listener, "POST", "/api/command", &command);
+        assert_ne!(reply["error"], "success");
+    }
+}
+
+#[test]
+fn sets_properties() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    let reply = json_request(&listener, "PUT", "/api/property/pause", &json!(true));
+    assert_eq!(reply["error"], "success");
+    assert!(player.get_property::<bool>("pause").unwrap());
+
+    let reply = json_request(&listener, "PUT", "/api/property/stream-record", &json!("/tmp/x"));
+    assert_ne!(reply["error"], "success");
+    assert_eq!(player.get_property::<String>("stream-record").unwrap(), "");
+}
+
+#[test]
+fn sets_allowed_properties() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0")
+        .settable("speed")
+        .start(&mut player)
+        .unwrap();
+
+    let reply = json_request(&listener, "POST", "/api/command", &json!(["set", "speed", "2"]));
+    assert_eq!(reply["error"], "success");
+    assert_eq!(player.get_property::<f64>("speed").unwrap(), 2.);
+}
+
+#[test]
+fn rejects_cross_origin_requests() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    let origin = [("Origin", "http://example.com")];
+    let (status, _) = request(&listener, "POST", "/api/command", &origin, r#"["set", "pause", "yes"]"#);
+    assert_eq!(status, 403);
+    assert!(!player.get_property::<bool>("pause").unwrap());
+
+    let same_origin = format!("http://{}", listener.address());
+    let (status, _) = request(
+        &listener,
+        "POST",
+        "/api/command",
+        &[("Origin", &same_origin)],
+        r#"["stop"]"#,
+    );
+    assert_eq!(status, 200);
+
+    let mut ws = format!("ws://{}/ws", listener.address()).into_client_request().unwrap();
+    ws.headers_mut()
+        .insert("Origin", HeaderValue::from_static("http://example.com"));
+    assert!(tungstenite::connect(ws).is_err());
+}
+
+#[test]
+fn rejects_other_hosts() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    // A page of a domain rebound to the loopback address
+    let rebound = [("Host", "evil.example"), ("Origin", "http://evil.example")];
+    let (status, _) = request(
+        &listener,
+        "POST",
+        "/api/command",
+        &rebound,
+        r#"["set", "pause", "yes"]"#,
+    );
+    assert_eq!(status, 403);
+    assert_eq!(request(&listener, "GET", "/api/state", &rebound[..1], "").0, 403);
+    assert!(!player.get_property::<bool>("pause").unwrap());
+
+    let localhost = format!("localhost:{}", listener.address().port());
+    assert_eq!(
+        request(&listener, "GET", "/api/state", &[("Host", &localhost)], "").0,
+        200
+    );
+}
+
+#[test]
+fn requires_token() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0")
+        .token("secret")
+        .start(&mut player)
+        .unwrap();
+
+    assert_eq!(request(&listener, "GET", "/api/state", &[], "").0, 401);
+    assert_eq!(request(&listener, "GET", "/api/state?token=guess", &[], "").0, 401);
+    assert_eq!(request(&listener, "GET", "/api/state?token=secret", &[], "").0, 200);
+
+    let result = RemoteServer::new("0.0.0.0:0").start(&mut player);
+    assert_eq!(result.err().map(|e| e.kind()), Some(ErrorKind::PermissionDenied));
+}
+
+#[test]
+fn pushes_changes_over_websocket() {
+    let mut player = TestPlayer::new().unwrap();
+    let listener = RemoteServer::new("127.0.0.1:0").start(&mut player).unwrap();
+
+    let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", listener.address())).unwrap();
+    assert_eq!(read_json(&mut socket)["type"], "state");
+
+    let command = json!({ "id": 1, "command": ["set", "volume", "40"] });
+    socket.send(Message::Text(command.to_string())).unwrap();
+    let (mut replied, mut pushed) = (false, false);
+    while !(replied && pushed) {
+        let message = read_json(&mut socket);
+        match message["type"].as_str() {
+            Some("reply") => {
+                assert_eq!(message["id"], 1);
+                assert_eq!(message["error"], "success");
+                replied = true;
+            }
+            Some("property") if message["name"] == "volume" && message["data"] == json!(40.0) => pushed = true,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use mpv_client::commands::ScreenshotMode;
+use mpv_client::screenshot::extract_frames;
+use mpv_client_test::{testsrc, TestPlayer};
+
+use std::time::Duration;
+
+#[test]
+fn takes_raw_screenshots() {
+    let mut player = TestPlayer::with_options(&[("pause", "yes")]).unwrap();
+    player.load(&testsrc(Duration::from_secs(2))).unwrap();
+
+    let image = player.screenshot_raw(ScreenshotMode::Video).unwrap();
+    assert_eq!((image.width, image.height), (320, 240));
+    assert!(image.stride >= image.width * image.pixel_size());
+    assert_eq!(image.to_rgba().unwrap().len(), 320 * 240 * 4);
+}
+
+#[test]
+fn extracts_frames_as_png() {
+    let mut player = TestPlayer::with_options(&[("pause", "yes")]).unwrap();
+    player.load(&testsrc(Duration::from_secs(5))).unwrap();
+
+    let frames = extract_frames(&mut player, &[1., 3.], ScreenshotMode::Video).unwrap();
+    assert_eq!(frames.len(), 2);
+    // The time counter of testsrc changes the picture
+    assert_ne!(frames[0].data, frames[1].data);
+    assert!(player.get_property::<bool>("pause").unwrap());
+
+    let png = frames[0].to_png().unwrap();
+    assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+}
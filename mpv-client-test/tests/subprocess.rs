@@ -0,0 +1,35 @@
+use mpv_client::subprocess::{KillReason, Subprocess, SubprocessOutput};
+use mpv_client::Node;
+use mpv_client_test::TestPlayer;
+
+#[test]
+fn runs_subprocess() {
+    let mut player = TestPlayer::new().unwrap();
+
+    let output = Subprocess::new(["echo", "hello"])
+        .playback_only(false)
+        .capture_stdout(true)
+        .run(&mut player)
+        .unwrap();
+    assert!(output.success());
+    assert_eq!(output.stdout_lossy(), "hello\n");
+}
+
+#[test]
+fn fails_on_missing_program() {
+    let mut player = TestPlayer::new().unwrap();
+
+    let output = Subprocess::new(["/nonexistent/program"])
+        .playback_only(false)
+        .run(&mut player)
+        .unwrap();
+    assert!(!output.success());
+    assert_eq!(output.kill_reason, Some(KillReason::Init));
+}
+
+#[test]
+fn fails_without_result() {
+    let output = SubprocessOutput::from_node(Node::None);
+    assert!(!output.success());
+    assert_eq!(output.status, -1);
+}
@@ -0,0 +1,213 @@
+use super::dispatch;
+use super::{ClientMessage, Handle, Result};
+
+use std::sync::{Arc, Mutex};
+
+pub(crate) type Callback = Arc<Mutex<dyn FnMut(&mut Handle, KeyEvent) + Send>>;
+
+/// Options of a key binding, see `Handle::add_key_binding`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBindingFlags {
+    /// Call the callback again while the key is held down (key repeat).
+    pub repeatable: bool,
+    /// Allow the key to be scaled, e.g. by high resolution mouse wheels.
+    pub scalable: bool,
+    /// Call the callback on every key state change (down, up, repeat), instead
+    /// of only when the key is pressed.
+    pub complex: bool,
+}
+
+/// The state of a key, as reported to a key binding callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyState {
+    /// The key was pressed down.
+    Down,
+    /// The key was released.
+    Up,
+    /// The key is held down and repeated.
+    Repeat,
+    /// The key was pressed and released at once (e.g. mouse wheel).
+    Press,
+}
+
+/// Data passed to a key binding callback.
+#[derive(Debug, Clone)]
+pub struct KeyEvent {
+    pub state: KeyState,
+    /// Whether the key is a mouse button.
+    pub is_mouse: bool,
+    /// Name of the key that triggered the binding.
+    pub key_name: Option<String>,
+    /// Text produced by the key, if any.
+    pub key_text: Option<String>,
+    /// Scale of the key press, for scalable bindings.
+    pub scale: f64,
+}
+
+pub(crate) struct Binding {
+    key: Option<String>,
+    flags: KeyBindingFlags,
+    forced: bool,
+    callback: Callback,
+}
+
+impl KeyEvent {
+    fn parse(args: &[&str]) -> Option<Self> {
+        let mut state = args.first()?.chars();
+        let state = match state.next()? {
+            'd' => KeyState::Down,
+            'u' => KeyState::Up,
+            'r' => KeyState::Repeat,
+            'p' => KeyState::Press,
+            _ => return None,
+        };
+        let is_mouse = args[0].chars().nth(1) == Some('m');
+        let arg = |i: usize| args.get(i).filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+        Some(Self {
+            state,
+            is_mouse,
+            key_name: arg(1),
+            key_text: arg(2),
+            scale: args.get(3).and_then(|s| s.parse().ok()).unwrap_or(1.),
+        })
+    }
+}
+
+impl Binding {
+    /// Whether the callback must be called for the given event.
+    fn accepts(&self, event: &KeyEvent) -> bool {
+        match event.state {
+            _ if self.flags.complex => true,
+            KeyState::Down | KeyState::Press => true,
+            KeyState::Repeat => self.flags.repeatable,
+            KeyState::Up => false,
+        }
+    }
+}
+
+impl Handle {
+    /// Register a key binding, like `mp.add_key_binding` in Lua.
+    ///
+    /// `key` is the default key (in input.conf syntax), it can be `None` if the
+    /// binding is only meant to be bound by the user with
+    /// `script-binding <client name>/<name>`. The bindings are put in the
+    /// `input_<client name>` input section, so they can be overridden by the
+    /// user's input.conf.
+    ///
+    /// The callback is run by `Handle::wait_event`, which consumes the
+    /// `key-binding` client messages of registered bindings.
+    pub fn add_key_binding<F>(
+        &mut self,
+        key: Option<&str>,
+        name: &str,
+        flags: KeyBindingFlags,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Handle, KeyEvent) + Send + 'static,
+    {
+        self.insert_key_binding(key, name, flags, false, Arc::new(Mutex::new(callback)))
+    }
+
+    /// Same as `Handle::add_key_binding`, but the bindings are put in the
+    /// `input_forced_<client name>` section, which takes precedence over the
+    /// user's input.conf.
+    pub fn add_forced_key_binding<F>(
+        &mut self,
+        key: Option<&str>,
+        name: &str,
+        flags: KeyBindingFlags,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&mut Handle, KeyEvent) + Send + 'static,
+    {
+        self.insert_key_binding(key, name, flags, true, Arc::new(Mutex::new(callback)))
+    }
+
+    /// Remove a key binding added with `Handle::add_key_binding` or
+    /// `Handle::add_forced_key_binding`.
+    pub fn remove_key_binding(&mut self, name: &str) -> Result<()> {
+        let handle = unsafe { self.as_ptr() };
+        match dispatch::remove_binding(handle, name) {
+            Some(binding) => self.update_input_section(binding.forced),
+            None => Ok(()),
+        }
+    }
+
+    fn insert_key_binding(
+        &mut self,
+        key: Option<&str>,
+        name: &str,
+        flags: KeyBindingFlags,
+        forced: bool,
+        callback: Callback,
+    ) -> Result<()> {
+        let handle = unsafe { self.as_ptr() };
+        let binding = Binding {
+            key: key.map(str::to_owned),
+            flags,
+            forced,
+            callback,
+        };
+        if let Some(previous) = dispatch::add_binding(handle, name.to_owned(), binding) {
+            if previous.forced != forced {
+                self.update_input_section(previous.forced)?;
+            }
+        }
+        self.update_input_section(forced)
+    }
+
+    /// Define and enable the input section holding the bindings.
+    fn update_input_section(&mut self, forced: bool) -> Result<()> {
+        let client = self.name();
+        let section = if forced {
+            format!("input_forced_{}", client)
+        } else {
+            format!("input_{}", client)
+        };
+
+        let contents: String = dispatch::bindings(unsafe { self.as_ptr() }, |name, binding| match &binding.key {
+            Some(key) if binding.forced == forced => {
+                let mut line = key.clone();
+                if binding.flags.repeatable {
+                    line.push_str(" repeatable");
+                }
+                if binding.flags.scalable {
+                    line.push_str(" scalable");
+                }
+                Some(format!("{} script-binding {}/{}\n", line, client, name))
+            }
+            _ => None,
+        })
+        .concat();
+
+        let mode = if forced { "force" } else { "default" };
+        self.command(["define-section", &section, &contents, mode])?;
+        self.command(["enable-section", &section, "allow-hide-cursor+allow-vo-dragging"])
+    }
+
+    /// Run the callback of the key binding targeted by a `key-binding` client
+    /// message. Return whether the message was handled.
+    pub(crate) fn dispatch_key_binding(&mut self, message: &ClientMessage) -> bool {
+        let args = message.args();
+        let (Some(&"key-binding"), Some(name)) = (args.first(), args.get(1)) else {
+            return false;
+        };
+        let Some((callback, accepts)) = dispatch::binding(unsafe { self.as_ptr() }, name, |binding| {
+            (
+                binding.callback.clone(),
+                KeyEvent::parse(&args[2..]).filter(|e| binding.accepts(e)),
+            )
+        }) else {
+            return false;
+        };
+
+        if let Some(event) = accepts {
+            let mut callback = callback.lock().unwrap_or_else(|e| e.into_inner());
+            callback(self, event);
+        }
+        true
+    }
+}
@@ -0,0 +1,626 @@
+//! Typed builders for common input commands.
+//!
+//! Each builder produces the arguments of `Handle::command` with
+//! `Command::args`, or of `Handle::command_node` with `Command::node`:
+//!
+//! ```ignore
+//! use mpv_client::commands::{Command, Seek};
+//!
+//! handle.command(Seek::relative(-5.).exact().args())?;
+//! ```
+
+use super::Node;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// An input command, see the builders of this module.
+pub trait Command {
+    /// The positional arguments of the command, for `Handle::command`.
+    fn args(&self) -> Vec<String>;
+
+    /// The command as a node, for `Handle::command_node`. By default it's the
+    /// array of `Command::args`.
+    fn node(&self) -> Node {
+        Node::Array(self.args().into_iter().map(Node::String).collect())
+    }
+}
+
+/// Where `LoadFile` puts the file in the playlist.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LoadFileMode {
+    /// Stop playback and play the file now.
+    #[default]
+    Replace,
+    /// Append the file to the playlist.
+    Append,
+    /// Append the file, and play it if nothing is playing.
+    AppendPlay,
+    /// Insert the file after the current entry.
+    InsertNext,
+    /// Insert the file after the current entry, and play it if nothing is
+    /// playing.
+    InsertNextPlay,
+    /// Insert the file at the given playlist index.
+    InsertAt(i64),
+    /// Insert the file at the given playlist index, and play it if nothing is
+    /// playing.
+    InsertAtPlay(i64),
+}
+
+/// `loadfile <url> <flags> <index> <options>`, needs mpv 0.38 or newer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadFile {
+    url: String,
+    mode: LoadFileMode,
+    options: Vec<(String, String)>,
+}
+
+/// How `Seek` interprets its target.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SeekMode {
+    /// Seconds from the current position.
+    #[default]
+    Relative,
+    /// Position in seconds.
+    Absolute,
+    /// Percent of the duration from the current position.
+    RelativePercent,
+    /// Position in percent of the duration.
+    AbsolutePercent,
+}
+
+/// Precision of `Seek`, overriding the `hr-seek` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekPrecision {
+    /// Seek to the exact position (slower).
+    Exact,
+    /// Seek to the nearest keyframe.
+    Keyframes,
+}
+
+/// `seek <target> <flags>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Seek {
+    target: f64,
+    mode: SeekMode,
+    precision: Option<SeekPrecision>,
+}
+
+/// What `Screenshot` captures.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ScreenshotMode {
+    /// The video with subtitles.
+    #[default]
+    Subtitles,
+    /// The video only.
+    Video,
+    /// The scaled window, with OSD and subtitles.
+    Window,
+}
+
+/// `screenshot <flags>`, or `screenshot-to-file <filename> <flags>` when a
+/// file is given.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Screenshot {
+    mode: ScreenshotMode,
+    each_frame: bool,
+    file: Option<String>,
+}
+
+/// The kind of track added by `TrackAdd`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackKind {
+    Audio,
+    Sub,
+    Video,
+}
+
+/// How `TrackAdd` selects the added track.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TrackFlag {
+    /// Select the track.
+    #[default]
+    Select,
+    /// Don't select the track, unless no track of this kind is selected.
+    Auto,
+    /// Select the track, or an already added track with the same url.
+    Cached,
+}
+
+/// `sub-add`, `audio-add` or `video-add <url> <flags> <title> <lang>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackAdd {
+    kind: TrackKind,
+    url: String,
+    flag: TrackFlag,
+    title: Option<String>,
+    lang: Option<String>,
+}
+
+/// `playlist-move <index1> <index2>`: move the entry at `from` so it takes the
+/// place of the entry at `to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaylistMove {
+    pub from: i64,
+    pub to: i64,
+}
+
+/// `cycle-values [!reverse] <property> <value1> <value2> ...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CycleValues {
+    property: String,
+    values: Vec<String>,
+    reverse: bool,
+}
+
+/// The filter chain changed by `Filters`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterChain {
+    Audio,
+    Video,
+}
+
+/// The change made to a filter chain by `Filters`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterOperation {
+    /// Replace the chain with the given filters.
+    Set(String),
+    /// Append a filter, even if it's already in the chain.
+    Append(String),
+    /// Append the given filters.
+    Add(String),
+    /// Prepend the given filters.
+    Pre(String),
+    /// Remove the given filters.
+    Remove(String),
+    /// Add the filters if they aren't in the chain, remove them otherwise.
+    Toggle(String),
+    /// Remove every filter.
+    Clear,
+}
+
+/// `af` or `vf <operation> <value>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filters {
+    pub chain: FilterChain,
+    pub operation: FilterOperation,
+}
+
+/// `show-text <text> <duration> <level>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShowText {
+    text: String,
+    duration: Option<Duration>,
+    level: Option<u8>,
+}
+
+impl LoadFile {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            mode: LoadFileMode::default(),
+            options: Vec::new(),
+        }
+    }
+
+    pub fn mode(mut self, mode: LoadFileMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set a per-file option, e.g. `start` or `sub-file`.
+    pub fn option(mut self, name: &str, value: &str) -> Self {
+        self.options.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    fn flags(&self) -> (&'static str, i64) {
+        match self.mode {
+            LoadFileMode::Replace => ("replace", -1),
+            LoadFileMode::Append => ("append", -1),
+            LoadFileMode::AppendPlay => ("append-play", -1),
+            LoadFileMode::InsertNext => ("insert-next", -1),
+            LoadFileMode::InsertNextPlay => ("insert-next-play", -1),
+            LoadFileMode::InsertAt(index) => ("insert-at", index),
+            LoadFileMode::InsertAtPlay(index) => ("insert-at-play", index),
+        }
+    }
+}
+
+impl Command for LoadFile {
+    /// Options are joined as `name=value,...`, so values can't contain commas
+    /// here, use `Command::node` for those.
+    fn args(&self) -> Vec<String> {
+        let (flags, index) = self.flags();
+        let options: Vec<String> = self
+            .options
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        vec![
+            "loadfile".to_owned(),
+            self.url.clone(),
+            flags.to_owned(),
+            index.to_string(),
+            options.join(","),
+        ]
+    }
+
+    fn node(&self) -> Node {
+        let (flags, index) = self.flags();
+        let options = self
+            .options
+            .iter()
+            .map(|(name, value)| (name.clone(), Node::String(value.clone())))
+            .collect();
+        let mut args = HashMap::new();
+        args.insert("name".to_owned(), Node::String("loadfile".to_owned()));
+        args.insert("url".to_owned(), Node::String(self.url.clone()));
+        args.insert("flags".to_owned(), Node::String(flags.to_owned()));
+        args.insert("index".to_owned(), Node::Int(index));
+        args.insert("options".to_owned(), Node::Map(options));
+        Node::Map(args)
+    }
+}
+
+impl Seek {
+    pub fn new(target: f64, mode: SeekMode) -> Self {
+        Self {
+            target,
+            mode,
+            precision: None,
+        }
+    }
+
+    /// Seek by `seconds` from the current position.
+    pub fn relative(seconds: f64) -> Self {
+        Self::new(seconds, SeekMode::Relative)
+    }
+
+    /// Seek to the position `seconds`.
+    pub fn absolute(seconds: f64) -> Self {
+        Self::new(seconds, SeekMode::Absolute)
+    }
+
+    /// Seek to `percent` of the duration.
+    pub fn percent(percent: f64) -> Self {
+        Self::new(percent, SeekMode::AbsolutePercent)
+    }
+
+    pub fn exact(mut self) -> Self {
+        self.precision = Some(SeekPrecision::Exact);
+        self
+    }
+
+    pub fn keyframes(mut self) -> Self {
+        self.precision = Some(SeekPrecision::Keyframes);
+        self
+    }
+}
+
+impl Command for Seek {
+    fn args(&self) -> Vec<String> {
+        let mut flags = String::from(match self.mode {
+            SeekMode::Relative => "relative",
+            SeekMode::Absolute => "absolute",
+            SeekMode::RelativePercent => "relative-percent",
+            SeekMode::AbsolutePercent => "absolute-percent",
+        });
+        match self.precision {
+            Some(SeekPrecision::Exact) => flags.push_str("+exact"),
+            Some(SeekPrecision::Keyframes) => flags.push_str("+keyframes"),
+            None => {}
+        }
+        vec!["seek".to_owned(), self.target.to_string(), flags]
+    }
+}
+
+impl ScreenshotMode {
+    /// The flag of the screenshot commands.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Subtitles => "subtitles",
+            Self::Video => "video",
+            Self::Window => "window",
+        }
+    }
+}
+
+impl Screenshot {
+    pub fn new(mode: ScreenshotMode) -> Self {
+        Self {
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Take a screenshot of every frame until the command is run again. Not
+    /// supported when saving to a file.
+    pub fn each_frame(mut self) -> Self {
+        self.each_frame = true;
+        self
+    }
+
+    /// Save the screenshot to `file`, with the format guessed from the
+    /// extension, instead of the `screenshot-dir`.
+    pub fn to_file(mut self, file: &str) -> Self {
+        self.file = Some(file.to_owned());
+        self
+    }
+}
+
+impl Command for Screenshot {
+    fn args(&self) -> Vec<String> {
+        let mode = self.mode.as_str();
+        match &self.file {
+            Some(file) => vec!["screenshot-to-file".to_owned(), file.clone(), mode.to_owned()],
+            None if self.each_frame => vec!["screenshot".to_owned(), format!("{}+each-frame", mode)],
+            None => vec!["screenshot".to_owned(), mode.to_owned()],
+        }
+    }
+}
+
+impl TrackAdd {
+    pub fn new(kind: TrackKind, url: &str) -> Self {
+        Self {
+            kind,
+            url: url.to_owned(),
+            flag: TrackFlag::default(),
+            title: None,
+            lang: None,
+        }
+    }
+
+    pub fn flag(mut self, flag: TrackFlag) -> Self {
+        self.flag = flag;
+        self
+    }
+
+    /// Set the title of the track shown in the track lists.
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        self
+    }
+
+    pub fn lang(mut self, lang: &str) -> Self {
+        self.lang = Some(lang.to_owned());
+        self
+    }
+}
+
+impl Command for TrackAdd {
+    fn args(&self) -> Vec<String> {
+        let name = match self.kind {
+            TrackKind::Audio => "audio-add",
+            TrackKind::Sub => "sub-add",
+            TrackKind::Video => "video-add",
+        };
+        let flag = match self.flag {
+            TrackFlag::Select => "select",
+            TrackFlag::Auto => "auto",
+            TrackFlag::Cached => "cached",
+        };
+        let mut args = vec![name.to_owned(), self.url.clone(), flag.to_owned()];
+        if self.title.is_some() || self.lang.is_some() {
+            args.push(self.title.clone().unwrap_or_default());
+        }
+        if let Some(lang) = &self.lang {
+            args.push(lang.clone());
+        }
+        args
+    }
+}
+
+impl Command for PlaylistMove {
+    fn args(&self) -> Vec<String> {
+        vec!["playlist-move".to_owned(), self.from.to_string(), self.to.to_string()]
+    }
+}
+
+impl CycleValues {
+    pub fn new<I, S>(property: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            property: property.to_owned(),
+            values: values.into_iter().map(Into::into).collect(),
+            reverse: false,
+        }
+    }
+
+    /// Cycle through the values backwards.
+    pub fn reverse(mut self) -> Self {
+        self.reverse = true;
+        self
+    }
+}
+
+impl Command for CycleValues {
+    fn args(&self) -> Vec<String> {
+        let mut args = vec!["cycle-values".to_owned()];
+        if self.reverse {
+            args.push("!reverse".to_owned());
+        }
+        args.push(self.property.clone());
+        args.extend(self.values.iter().cloned());
+        args
+    }
+}
+
+impl Filters {
+    pub fn new(chain: FilterChain, operation: FilterOperation) -> Self {
+        Self { chain, operation }
+    }
+}
+
+impl Command for Filters {
+    fn args(&self) -> Vec<String> {
+        let name = match self.chain {
+            FilterChain::Audio => "af",
+            FilterChain::Video => "vf",
+        };
+        let (operation, value) = match &self.operation {
+            FilterOperation::Set(value) => ("set", value.as_str()),
+            FilterOperation::Append(value) => ("append", value.as_str()),
+            FilterOperation::Add(value) => ("add", value.as_str()),
+            FilterOperation::Pre(value) => ("pre", value.as_str()),
+            FilterOperation::Remove(value) => ("remove", value.as_str()),
+            FilterOperation::Toggle(value) => ("toggle", value.as_str()),
+            FilterOperation::Clear => ("clr", ""),
+        };
+        vec![name.to_owned(), operation.to_owned(), value.to_owned()]
+    }
+}
+
+impl ShowText {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            duration: None,
+            level: None,
+        }
+    }
+
+    /// Show the text for `duration`, instead of the `osd-duration`.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = Some(duration);
+        self
+    }
+
+    /// Only show the text if the `osd-level` is at least `level`.
+    pub fn level(mut self, level: u8) -> Self {
+        self.level = Some(level);
+        self
+    }
+}
+
+impl Command for ShowText {
+    fn args(&self) -> Vec<String> {
+        let duration = self.duration.map_or(-1, |d| d.as_millis() as i64);
+        let mut args = vec!["show-text".to_owned(), self.text.clone(), duration.to_string()];
+        if let Some(level) = self.level {
+            args.push(level.to_string());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_args<C: Command>(command: C, expected: &[&str]) {
+        assert_eq!(command.args(), expected);
+    }
+
+    #[test]
+    fn load_file() {
+        assert_args(LoadFile::new("a.mkv"), &["loadfile", "a.mkv", "replace", "-1", ""]);
+        assert_args(
+            LoadFile::new("a.mkv")
+                .mode(LoadFileMode::InsertAtPlay(2))
+                .option("start", "10")
+                .option("pause", "yes"),
+            &["loadfile", "a.mkv", "insert-at-play", "2", "start=10,pause=yes"],
+        );
+
+        let node = LoadFile::new("a.mkv").option("start", "1,5").node();
+        let Node::Map(args) = node else {
+            panic!("not a map: {:?}", node);
+        };
+        assert_eq!(args["name"], Node::String(String::from("loadfile")));
+        assert_eq!(args["index"], Node::Int(-1));
+        assert_eq!(
+            args["options"],
+            Node::Map(HashMap::from([(
+                String::from("start"),
+                Node::String(String::from("1,5"))
+            )]))
+        );
+    }
+
+    #[test]
+    fn seek() {
+        assert_args(Seek::relative(-5.), &["seek", "-5", "relative"]);
+        assert_args(Seek::absolute(12.5).exact(), &["seek", "12.5", "absolute+exact"]);
+        assert_args(
+            Seek::percent(50.).keyframes(),
+            &["seek", "50", "absolute-percent+keyframes"],
+        );
+        assert_args(
+            Seek::new(10., SeekMode::RelativePercent),
+            &["seek", "10", "relative-percent"],
+        );
+    }
+
+    #[test]
+    fn screenshot() {
+        assert_args(Screenshot::default(), &["screenshot", "subtitles"]);
+        assert_args(
+            Screenshot::new(ScreenshotMode::Video).each_frame(),
+            &["screenshot", "video+each-frame"],
+        );
+        assert_args(
+            Screenshot::new(ScreenshotMode::Window).to_file("a.png"),
+            &["screenshot-to-file", "a.png", "window"],
+        );
+    }
+
+    #[test]
+    fn track_add() {
+        assert_args(TrackAdd::new(TrackKind::Sub, "a.srt"), &["sub-add", "a.srt", "select"]);
+        assert_args(
+            TrackAdd::new(TrackKind::Audio, "a.flac")
+                .flag(TrackFlag::Auto)
+                .title("Commentary"),
+            &["audio-add", "a.flac", "auto", "Commentary"],
+        );
+        assert_args(
+            TrackAdd::new(TrackKind::Video, "a.mkv")
+                .flag(TrackFlag::Cached)
+                .lang("en"),
+            &["video-add", "a.mkv", "cached", "", "en"],
+        );
+    }
+
+    #[test]
+    fn playlist_move() {
+        assert_args(PlaylistMove { from: 3, to: 0 }, &["playlist-move", "3", "0"]);
+    }
+
+    #[test]
+    fn cycle_values() {
+        assert_args(
+            CycleValues::new("hwdec", ["auto", "no"]),
+            &["cycle-values", "hwdec", "auto", "no"],
+        );
+        assert_args(
+            CycleValues::new("speed", ["1", "2"]).reverse(),
+            &["cycle-values", "!reverse", "speed", "1", "2"],
+        );
+    }
+
+    #[test]
+    fn filters() {
+        assert_args(
+            Filters::new(FilterChain::Video, FilterOperation::Toggle(String::from("hflip"))),
+            &["vf", "toggle", "hflip"],
+        );
+        assert_args(
+            Filters::new(FilterChain::Audio, FilterOperation::Clear),
+            &["af", "clr", ""],
+        );
+    }
+
+    #[test]
+    fn show_text() {
+        assert_args(ShowText::new("Hello"), &["show-text", "Hello", "-1"]);
+        assert_args(
+            ShowText::new("Hello").duration(Duration::from_secs(2)).level(1),
+            &["show-text", "Hello", "2000", "1"],
+        );
+    }
+}
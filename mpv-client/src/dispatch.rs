@@ -0,0 +1,368 @@
+//! State kept per client handle by `Handle::wait_event`.
+//!
+//! `Handle` only wraps the raw `mpv_handle`, so anything the dispatcher must
+//! remember between calls lives here, keyed by the handle address.
+
+use super::binding::Binding;
+use super::menu::MenuState;
+use super::message::Handler;
+use super::osd::{OsdDimensions, OverlayEntry};
+use super::pending::SharedSlot;
+use super::prompt::PromptState;
+#[cfg(feature = "rpc")]
+use super::rpc::{Method, Pending};
+use super::timer::TimerEntry;
+use super::{mpv_format, mpv_handle, Handle, Property};
+
+#[cfg(feature = "rpc")]
+use std::collections::BTreeSet;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex, MutexGuard, RwLock};
+
+pub(crate) type PropertyCallback = Arc<Mutex<dyn FnMut(&mut Handle, &Property) + Send>>;
+pub(crate) type RemoveHook = Box<dyn FnOnce() + Send>;
+
+#[derive(Clone)]
+pub(crate) struct Observation {
+    pub(crate) reply: u64,
+    pub(crate) name: String,
+    pub(crate) format: mpv_format,
+}
+
+#[derive(Default)]
+pub(crate) struct State {
+    observations: Vec<Observation>,
+    resync: VecDeque<Observation>,
+    overflows: u64,
+    timers: Vec<Arc<TimerEntry>>,
+    bindings: BTreeMap<String, Binding>,
+    messages: BTreeMap<String, Handler>,
+    property_callbacks: BTreeMap<u64, PropertyCallback>,
+    pending_commands: BTreeMap<u64, SharedSlot>,
+    overlays: BTreeMap<i64, OverlayEntry>,
+    osd_dimensions: Option<OsdDimensions>,
+    menu: Option<Arc<Mutex<MenuState>>>,
+    prompt: Option<Arc<Mutex<PromptState>>>,
+    prompt_histories: BTreeMap<String, Vec<String>>,
+    #[cfg(feature = "rpc")]
+    rpc_methods: BTreeMap<String, Method>,
+    #[cfg(feature = "rpc")]
+    rpc_pending: BTreeMap<u64, Pending>,
+    #[cfg(feature = "rpc")]
+    rpc_next_id: u64,
+    /// The script messages of the RPC registered so far.
+    #[cfg(feature = "rpc")]
+    rpc_messages: BTreeSet<&'static str>,
+    alive: Option<Arc<RwLock<bool>>>,
+    remove_hooks: Vec<RemoveHook>,
+}
+
+/// The handle of a value which can outlive its client, like a timer or an
+/// overlay. The handle is only used while the client exists.
+#[derive(Clone)]
+pub(crate) struct HandleRef {
+    handle: *mut mpv_handle,
+    alive: Arc<RwLock<bool>>,
+}
+
+static STATES: Mutex<BTreeMap<usize, State>> = Mutex::new(BTreeMap::new());
+
+fn states() -> MutexGuard<'static, BTreeMap<usize, State>> {
+    STATES.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn with_state<R>(handle: *const mpv_handle, f: impl FnOnce(&mut State) -> R) -> R {
+    f(states().entry(handle as usize).or_default())
+}
+
+/// Forget everything about a handle that is being destroyed.
+pub(crate) fn remove(handle: *const mpv_handle) {
+    // Dropped once unlocked, the callbacks may own timers which lock the states.
+    let state = states().remove(&(handle as usize));
+    if let Some(mut state) = state {
+        if let Some(alive) = &state.alive {
+            // Wait for the users of the handle.
+            *alive.write().unwrap_or_else(|e| e.into_inner()) = false;
+        }
+        for hook in state.remove_hooks.drain(..) {
+            hook();
+        }
+    }
+}
+
+impl HandleRef {
+    pub(crate) fn new(handle: &mut Handle) -> Self {
+        let handle = unsafe { handle.as_mut_ptr() };
+        let alive = with_state(handle, |state| {
+            state.alive.get_or_insert_with(|| Arc::new(RwLock::new(true))).clone()
+        });
+        Self { handle, alive }
+    }
+
+    /// Run `f` with the handle, unless the client was destroyed or shut down.
+    /// The client is not destroyed until `f` returns.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut Handle) -> R) -> Option<R> {
+        let alive = self.alive.read().unwrap_or_else(|e| e.into_inner());
+        alive.then(|| f(Handle::from_ptr(self.handle)))
+    }
+}
+
+// The handle is only used while the client is alive, and the client API is
+// thread safe.
+unsafe impl Send for HandleRef {}
+unsafe impl Sync for HandleRef {}
+
+/// Call `hook` when the handle is destroyed or shut down.
+#[cfg(feature = "log")]
+pub(crate) fn on_remove(handle: *const mpv_handle, hook: RemoveHook) {
+    with_state(handle, |state| state.remove_hooks.push(hook));
+}
+
+pub(crate) fn observe(handle: *const mpv_handle, observation: Observation) {
+    with_state(handle, |state| state.observations.push(observation));
+}
+
+pub(crate) fn unobserve(handle: *const mpv_handle, reply: u64) {
+    with_state(handle, |state| {
+        state.observations.retain(|o| o.reply != reply);
+        state.resync.retain(|o| o.reply != reply);
+    });
+}
+
+/// Schedule a re-read of every observed property after events were dropped.
+pub(crate) fn overflow(handle: *const mpv_handle) {
+    with_state(handle, |state| {
+        state.overflows += 1;
+        state.resync = state.observations.iter().cloned().collect();
+    });
+}
+
+pub(crate) fn overflows(handle: *const mpv_handle) -> u64 {
+    states().get(&(handle as usize)).map_or(0, |state| state.overflows)
+}
+
+pub(crate) fn next_resync(handle: *const mpv_handle) -> Option<Observation> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.resync.pop_front())
+}
+
+pub(crate) fn add_timer(handle: *const mpv_handle, timer: Arc<TimerEntry>) {
+    with_state(handle, |state| state.timers.push(timer));
+}
+
+pub(crate) fn remove_timer(handle: *const mpv_handle, timer: &Arc<TimerEntry>) {
+    if let Some(state) = states().get_mut(&(handle as usize)) {
+        state.timers.retain(|t| !Arc::ptr_eq(t, timer));
+    }
+}
+
+pub(crate) fn timers(handle: *const mpv_handle) -> Vec<Arc<TimerEntry>> {
+    states()
+        .get(&(handle as usize))
+        .map_or_else(Vec::new, |state| state.timers.clone())
+}
+
+/// Register a key binding, return the binding it replaces.
+pub(crate) fn add_binding(handle: *const mpv_handle, name: String, binding: Binding) -> Option<Binding> {
+    with_state(handle, |state| state.bindings.insert(name, binding))
+}
+
+pub(crate) fn remove_binding(handle: *const mpv_handle, name: &str) -> Option<Binding> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.bindings.remove(name))
+}
+
+pub(crate) fn binding<R>(handle: *const mpv_handle, name: &str, f: impl FnOnce(&Binding) -> R) -> Option<R> {
+    states()
+        .get(&(handle as usize))
+        .and_then(|state| state.bindings.get(name))
+        .map(f)
+}
+
+pub(crate) fn bindings<R>(handle: *const mpv_handle, mut f: impl FnMut(&str, &Binding) -> Option<R>) -> Vec<R> {
+    states().get(&(handle as usize)).map_or_else(Vec::new, |state| {
+        state
+            .bindings
+            .iter()
+            .filter_map(|(name, binding)| f(name, binding))
+            .collect()
+    })
+}
+
+/// Register a property callback, return the reply number to observe it with.
+pub(crate) fn add_property_callback(handle: *const mpv_handle, callback: PropertyCallback) -> u64 {
+    with_state(handle, |state| {
+        let reply = (0..=u64::MAX)
+            .rev()
+            .find(|reply| !state.property_callbacks.contains_key(reply))
+            .unwrap_or_default();
+        state.property_callbacks.insert(reply, callback);
+        reply
+    })
+}
+
+pub(crate) fn remove_property_callback(handle: *const mpv_handle, reply: u64) -> Option<PropertyCallback> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.property_callbacks.remove(&reply))
+}
+
+pub(crate) fn property_callback(handle: *const mpv_handle, reply: u64) -> Option<PropertyCallback> {
+    states()
+        .get(&(handle as usize))
+        .and_then(|state| state.property_callbacks.get(&reply).cloned())
+}
+
+/// Register a pending async command, return the reply number to run it with.
+pub(crate) fn add_pending_command(handle: *const mpv_handle, slot: SharedSlot) -> u64 {
+    with_state(handle, |state| {
+        let reply = (0..=u64::MAX)
+            .rev()
+            .find(|reply| !state.pending_commands.contains_key(reply))
+            .unwrap_or_default();
+        state.pending_commands.insert(reply, slot);
+        reply
+    })
+}
+
+pub(crate) fn take_pending_command(handle: *const mpv_handle, reply: u64) -> Option<SharedSlot> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.pending_commands.remove(&reply))
+}
+
+pub(crate) fn add_message_handler(handle: *const mpv_handle, name: String, handler: Handler) {
+    with_state(handle, |state| state.messages.insert(name, handler));
+}
+
+pub(crate) fn remove_message_handler(handle: *const mpv_handle, name: &str) -> Option<Handler> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.messages.remove(name))
+}
+
+pub(crate) fn message_handler(handle: *const mpv_handle, name: &str) -> Option<Handler> {
+    states()
+        .get(&(handle as usize))
+        .and_then(|state| state.messages.get(name).cloned())
+}
+
+/// Register an overlay, return its lowest free `osd-overlay` id.
+pub(crate) fn add_overlay(handle: *const mpv_handle, overlay: OverlayEntry) -> i64 {
+    with_state(handle, |state| {
+        let id = (1..).find(|id| !state.overlays.contains_key(id)).unwrap_or_default();
+        state.overlays.insert(id, overlay);
+        id
+    })
+}
+
+pub(crate) fn remove_overlay(handle: *const mpv_handle, id: i64) -> Option<OverlayEntry> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.overlays.remove(&id))
+}
+
+pub(crate) fn with_overlay<R>(handle: *const mpv_handle, id: i64, f: impl FnOnce(&mut OverlayEntry) -> R) -> Option<R> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.overlays.get_mut(&id))
+        .map(f)
+}
+
+pub(crate) fn overlay_ids(handle: *const mpv_handle) -> Vec<i64> {
+    states()
+        .get(&(handle as usize))
+        .map_or_else(Vec::new, |state| state.overlays.keys().copied().collect())
+}
+
+pub(crate) fn osd_dimensions(handle: *const mpv_handle) -> Option<OsdDimensions> {
+    states().get(&(handle as usize)).and_then(|state| state.osd_dimensions)
+}
+
+pub(crate) fn set_osd_dimensions(handle: *const mpv_handle, dimensions: OsdDimensions) {
+    with_state(handle, |state| state.osd_dimensions = Some(dimensions));
+}
+
+/// Set the open menu, return the menu it replaces.
+pub(crate) fn set_menu(
+    handle: *const mpv_handle,
+    menu: Option<Arc<Mutex<MenuState>>>,
+) -> Option<Arc<Mutex<MenuState>>> {
+    with_state(handle, |state| std::mem::replace(&mut state.menu, menu))
+}
+
+pub(crate) fn menu(handle: *const mpv_handle) -> Option<Arc<Mutex<MenuState>>> {
+    states().get(&(handle as usize)).and_then(|state| state.menu.clone())
+}
+
+/// Set the open prompt, return the prompt it replaces.
+pub(crate) fn set_prompt(
+    handle: *const mpv_handle,
+    prompt: Option<Arc<Mutex<PromptState>>>,
+) -> Option<Arc<Mutex<PromptState>>> {
+    with_state(handle, |state| std::mem::replace(&mut state.prompt, prompt))
+}
+
+pub(crate) fn prompt(handle: *const mpv_handle) -> Option<Arc<Mutex<PromptState>>> {
+    states().get(&(handle as usize)).and_then(|state| state.prompt.clone())
+}
+
+pub(crate) fn prompt_history(handle: *const mpv_handle, name: &str) -> Vec<String> {
+    states()
+        .get(&(handle as usize))
+        .and_then(|state| state.prompt_histories.get(name).cloned())
+        .unwrap_or_default()
+}
+
+/// Add an entry to a prompt history, keeping at most `size` entries.
+pub(crate) fn push_prompt_history(handle: *const mpv_handle, name: &str, entry: String, size: usize) {
+    with_state(handle, |state| {
+        let history = state.prompt_histories.entry(name.to_owned()).or_default();
+        history.retain(|e| *e != entry);
+        history.push(entry);
+        if history.len() > size {
+            history.drain(..history.len() - size);
+        }
+    });
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn add_rpc_method(handle: *const mpv_handle, name: String, method: Method) -> Option<Method> {
+    with_state(handle, |state| state.rpc_methods.insert(name, method))
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn rpc_method(handle: *const mpv_handle, name: &str) -> Option<Method> {
+    states()
+        .get(&(handle as usize))
+        .and_then(|state| state.rpc_methods.get(name).cloned())
+}
+
+/// Remember that the script message `name` of the RPC is registered, return
+/// whether it wasn't yet.
+#[cfg(feature = "rpc")]
+pub(crate) fn add_rpc_message(handle: *const mpv_handle, name: &'static str) -> bool {
+    with_state(handle, |state| state.rpc_messages.insert(name))
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn next_rpc_id(handle: *const mpv_handle) -> u64 {
+    with_state(handle, |state| {
+        state.rpc_next_id += 1;
+        state.rpc_next_id
+    })
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn add_rpc_pending(handle: *const mpv_handle, id: u64, pending: Pending) {
+    with_state(handle, |state| state.rpc_pending.insert(id, pending));
+}
+
+#[cfg(feature = "rpc")]
+pub(crate) fn take_rpc_pending(handle: *const mpv_handle, id: u64) -> Option<Pending> {
+    states()
+        .get_mut(&(handle as usize))
+        .and_then(|state| state.rpc_pending.remove(&id))
+}
@@ -6,7 +6,7 @@ use std::ffi::{CStr, NulError};
 use std::fmt;
 use std::str::Utf8Error;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct Error(mpv_error);
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -14,6 +14,29 @@ impl Error {
     pub fn new(error: mpv_error) -> Self {
         Self(error)
     }
+
+    /// The mpv error code.
+    pub fn code(&self) -> mpv_error {
+        self.0
+    }
+
+    /// The description of the error, from `mpv_error_string()`.
+    pub(crate) fn message(&self) -> &'static str {
+        unsafe {
+            CStr::from_ptr(mpv_error_string(self.0))
+                .to_str()
+                .unwrap_or("unknown error")
+        }
+    }
+
+    /// The error whose `mpv_error_string()` is `message`, as reported by the
+    /// JSON IPC protocol, or `MPV_ERROR_GENERIC` if there is none.
+    #[cfg(feature = "ipc")]
+    pub(crate) fn from_message(message: &str) -> Self {
+        (mpv_error_MPV_ERROR_GENERIC..0)
+            .find(|&error| Self::new(error).message() == message)
+            .map_or(Self::new(mpv_error_MPV_ERROR_GENERIC), Self::new)
+    }
 }
 
 impl From<NulError> for Error {
@@ -30,11 +53,6 @@ impl From<Utf8Error> for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let err = unsafe {
-            CStr::from_ptr(mpv_error_string(self.0))
-                .to_str()
-                .unwrap_or("unknown error")
-        };
-        write!(f, "[{}] {}", self.0 as i32, err)
+        write!(f, "[{}] {}", self.0 as i32, self.message())
     }
 }
@@ -1,9 +1,14 @@
 use super::Result;
-use super::{mpv_format_MPV_FORMAT_NONE, mpv_free, mpv_free_node_contents, mpv_node, mpv_node__bindgen_ty_1};
+use super::{
+    mpv_format_MPV_FORMAT_DOUBLE, mpv_format_MPV_FORMAT_FLAG, mpv_format_MPV_FORMAT_INT64, mpv_format_MPV_FORMAT_NODE,
+    mpv_format_MPV_FORMAT_NONE, mpv_format_MPV_FORMAT_STRING, mpv_free, mpv_free_node_contents, mpv_node,
+    mpv_node__bindgen_ty_1,
+};
 
+use std::cell::RefCell;
 use std::ffi::{c_char, c_int, c_void, CStr, CString};
 
-use super::node::{from_mpv_node, to_mpv_node, Node};
+use super::node::{free_mpv_node, from_mpv_node, to_mpv_node, Node};
 
 pub trait Format: Sized + Default {
     const MPV_FORMAT: u32;
@@ -12,6 +17,23 @@ pub trait Format: Sized + Default {
     fn from_mpv<F: Fn(*mut c_void) -> Result<()>>(fun: F) -> Result<Self>;
 }
 
+/// Convert a value to a node, through its mpv representation.
+pub(crate) fn to_node<T: Format>(data: T) -> Result<Node> {
+    let node = RefCell::new(Node::None);
+    data.to_mpv(|ptr| {
+        *node.borrow_mut() = match T::MPV_FORMAT {
+            mpv_format_MPV_FORMAT_STRING => Node::String(String::from_ptr(ptr)?),
+            mpv_format_MPV_FORMAT_FLAG => Node::Bool(bool::from_ptr(ptr)?),
+            mpv_format_MPV_FORMAT_INT64 => Node::Int(i64::from_ptr(ptr)?),
+            mpv_format_MPV_FORMAT_DOUBLE => Node::Double(f64::from_ptr(ptr)?),
+            mpv_format_MPV_FORMAT_NODE => Node::from_ptr(ptr)?,
+            _ => Node::None,
+        };
+        Ok(())
+    })?;
+    Ok(node.into_inner())
+}
+
 impl Format for String {
     const MPV_FORMAT: u32 = 1;
 
@@ -96,16 +118,15 @@ impl Format for Node {
             return Ok(Node::None);
         }
 
+        // The node is owned by the event, it is released by the next wait.
         let node = unsafe { &mut *(ptr as *mut mpv_node) };
-        let result = from_mpv_node(node);
-        unsafe { mpv_free_node_contents(node) };
-        Ok(result)
+        Ok(from_mpv_node(node))
     }
 
     fn to_mpv<F: Fn(*mut c_void) -> Result<()>>(self, fun: F) -> Result<()> {
         let mpv_node_ptr = to_mpv_node(&self);
         let res = fun(mpv_node_ptr as *mut c_void);
-        unsafe { mpv_free_node_contents(mpv_node_ptr) };
+        unsafe { free_mpv_node(mpv_node_ptr) };
         res
     }
 
@@ -0,0 +1,99 @@
+//! The parts shared by the menu and the prompt: an overlay on top of the OSD,
+//! and forced key bindings taking over the keyboard while it is open.
+
+use super::osd::{AssBuilder, OsdDimensions, Overlay};
+use super::{Handle, KeyBindingFlags, KeyState, Result};
+
+/// Z-order of the overlay, above the overlays of scripts.
+const Z: i64 = 100;
+
+const FONT_SCALE: f64 = 1. / 32.;
+
+/// The key receiving typed text.
+const ANY_UNICODE: &str = "ANY_UNICODE";
+
+/// A key, the name of its binding, and its action.
+pub(crate) type Binding<A> = (&'static str, &'static str, A);
+
+/// An overlay with its key bindings, removed by `InputOverlay::close`.
+pub(crate) struct InputOverlay {
+    overlay: Overlay,
+    names: Vec<&'static str>,
+}
+
+impl InputOverlay {
+    /// Add the overlay drawn by `render`, and bind the keys of `bindings`.
+    /// `on_key` is called with the action and the text of a key, keys bound
+    /// to `ANY_UNICODE` are ignored when they don't type any text.
+    pub(crate) fn open<A, R, F>(
+        handle: &mut Handle,
+        bindings: &'static [Binding<A>],
+        render: R,
+        on_key: F,
+    ) -> Result<Self>
+    where
+        A: Copy + Send + 'static,
+        R: FnMut(&OsdDimensions) -> String + Send + 'static,
+        F: Fn(&mut Handle, A, Option<String>) + Clone + Send + 'static,
+    {
+        let mut input = Self {
+            overlay: handle.add_overlay(Z, render)?,
+            names: Vec::with_capacity(bindings.len()),
+        };
+        for &(key, name, action) in bindings {
+            let typing = key == ANY_UNICODE;
+            let flags = KeyBindingFlags {
+                repeatable: true,
+                complex: typing,
+                ..KeyBindingFlags::default()
+            };
+            let on_key = on_key.clone();
+            let added = handle.add_forced_key_binding(Some(key), name, flags, move |handle, event| {
+                match (typing, event.state, event.key_text) {
+                    (true, KeyState::Up, _) | (true, _, None) => {}
+                    (_, _, text) => on_key(handle, action, text),
+                }
+            });
+            if let Err(e) = added {
+                let _ = input.close(handle);
+                return Err(e);
+            }
+            input.names.push(name);
+        }
+        Ok(input)
+    }
+
+    /// The id of the overlay, to render it again with `Handle::render_overlay`.
+    pub(crate) fn id(&self) -> i64 {
+        self.overlay.id()
+    }
+
+    /// Remove the key bindings and the overlay.
+    pub(crate) fn close(self, handle: &mut Handle) -> Result<()> {
+        let mut result = Ok(());
+        for name in &self.names {
+            result = result.and(handle.remove_key_binding(name));
+        }
+        result
+    }
+}
+
+/// The font size for the size of the OSD.
+pub(crate) fn font_size(dimensions: &OsdDimensions) -> f64 {
+    (dimensions.h as f64 * FONT_SCALE).max(12.)
+}
+
+/// Draw the translucent background of the overlay, from `(x0, y0)` to
+/// `(x1, y1)`.
+pub(crate) fn background(ass: &mut AssBuilder, x0: f64, y0: f64, x1: f64, y1: f64) {
+    ass.new_event()
+        .an(7)
+        .pos(0., 0.)
+        .border(0.)
+        .shadow(0.)
+        .color(0x000000)
+        .alpha(0x60)
+        .draw_start()
+        .rect_cw(x0, y0, x1, y1)
+        .draw_stop();
+}
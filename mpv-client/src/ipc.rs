@@ -0,0 +1,865 @@
+//! Control of a running mpv through its JSON IPC socket.
+//!
+//! `IpcClient` connects to the Unix socket created by `--input-ipc-server`
+//! and speaks mpv's JSON protocol, one message per line. It provides the
+//! operations of `Handle` and returns the same `Event`s, and implements
+//! `Player`, so the same code can drive either libmpv or a remote player:
+//!
+//! ```ignore
+//! use mpv_client::ipc::IpcClient;
+//! use mpv_client::Event;
+//!
+//! let mut client = IpcClient::connect("/tmp/mpv.sock")?;
+//! client.observe_property::<bool>(1, "pause")?;
+//! client.command(["cycle", "pause"])?;
+//! while let Event::PropertyChange(1, property) = client.wait_event(-1.) {
+//!     println!("pause: {:?}", property.data::<bool>());
+//! }
+//! ```
+//!
+//! The protocol has no hooks, `Player::hook_add` fails with
+//! `MPV_ERROR_NOT_IMPLEMENTED`.
+//!
+//! `IpcServer` is the other side: it serves the protocol over a Unix socket by
+//! translating the requests to `Handle` calls, for embedders that create their
+//! own `Client`. It can restrict the connections and add custom commands:
+//!
+//! ```ignore
+//! use mpv_client::ipc::IpcServer;
+//! use serde_json::json;
+//!
+//! let listener = IpcServer::new("/tmp/my-player.sock")
+//!     .accept(|stream| is_same_user(stream))
+//!     .method("library-search", |_, params| Ok(json!(search(&params[0]))))
+//!     .start(&mut client)?;
+//! ```
+
+use super::format::to_node;
+use super::json::{from_json, to_json};
+use super::owned::{OwnedProperty, Value};
+use super::{
+    mpv_client_api_version, mpv_error_MPV_ERROR_GENERIC, mpv_error_MPV_ERROR_INVALID_PARAMETER,
+    mpv_error_MPV_ERROR_NOT_IMPLEMENTED, mpv_error_MPV_ERROR_PROPERTY_FORMAT, mpv_format, mpv_format_MPV_FORMAT_NODE,
+    mpv_get_time_us, mpv_handle, mpv_wakeup,
+};
+use super::{
+    Client, ClientMessage, EndFile, EndFileReason, Error, Event, EventKind, Format, Handle, LogLevel, LogMessage, Node,
+    Player, Property, Result, StartFile,
+};
+
+use serde_json::{json, Map, Value as Json};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Shortest wait on the socket, a zero timeout means no timeout.
+const MIN_READ_TIMEOUT: Duration = Duration::from_millis(1);
+
+/// How often the server checks whether it was stopped.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A client of the JSON IPC protocol, see the module documentation.
+pub struct IpcClient {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+    events: VecDeque<Event>,
+    observed: HashMap<u64, mpv_format>,
+    next_request_id: i64,
+    closed: bool,
+}
+
+/// Error of an `IpcClient` request.
+#[derive(Debug)]
+pub enum IpcError {
+    /// The socket failed.
+    Io(io::Error),
+    /// The player closed the connection.
+    Closed,
+    /// The player answered with an error.
+    Mpv(Error),
+    /// The player sent something that isn't valid JSON.
+    InvalidReply(String),
+}
+
+impl fmt::Display for IpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "socket error: {}", e),
+            Self::Closed => f.write_str("connection closed"),
+            Self::Mpv(e) => write!(f, "mpv error: {}", e),
+            Self::InvalidReply(message) => write!(f, "invalid reply: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for IpcError {}
+
+impl From<io::Error> for IpcError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<Error> for IpcError {
+    fn from(e: Error) -> Self {
+        Self::Mpv(e)
+    }
+}
+
+/// Errors of the connection itself become `MPV_ERROR_GENERIC`.
+impl From<IpcError> for Error {
+    fn from(e: IpcError) -> Self {
+        match e {
+            IpcError::Mpv(e) => e,
+            _ => Error::new(mpv_error_MPV_ERROR_GENERIC),
+        }
+    }
+}
+
+fn string_args<I, S>(args: I) -> Json
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    Json::Array(args.into_iter().map(|arg| json!(arg.as_ref())).collect())
+}
+
+fn str_field<'a>(message: &'a Map<String, Json>, key: &str) -> &'a str {
+    message.get(key).and_then(Json::as_str).unwrap_or_default()
+}
+
+fn i64_field(message: &Map<String, Json>, key: &str) -> i64 {
+    message.get(key).and_then(Json::as_i64).unwrap_or_default()
+}
+
+impl IpcClient {
+    /// Connect to the socket at `path`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        UnixStream::connect(path).map(Self::from_stream)
+    }
+
+    /// Use an already connected socket, e.g. one end of a `UnixStream::pair`
+    /// whose other end was passed to mpv with `--input-ipc-client`.
+    pub fn from_stream(stream: UnixStream) -> Self {
+        Self {
+            stream,
+            buffer: Vec::new(),
+            events: VecDeque::new(),
+            observed: HashMap::new(),
+            next_request_id: 1,
+            closed: false,
+        }
+    }
+
+    /// Send a command given as an array of strings.
+    pub fn command<I, S>(&mut self, args: I) -> std::result::Result<(), IpcError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.request(string_args(args)).map(|_| ())
+    }
+
+    /// Send a command given as an array of strings, and return its result.
+    pub fn command_ret<I, S>(&mut self, args: I) -> std::result::Result<Node, IpcError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.request(string_args(args)).map(|data| from_json(&data))
+    }
+
+    /// Send a command given as a node, with positional (array) or named (map)
+    /// arguments, and return its result.
+    pub fn command_node(&mut self, args: Node) -> std::result::Result<Node, IpcError> {
+        self.request(to_json(&args)).map(|data| from_json(&data))
+    }
+
+    pub fn get_property<T: Format>(&mut self, name: &str) -> std::result::Result<T, IpcError> {
+        let data = from_json(&self.request(json!(["get_property", name]))?);
+        Value::convert(&data, T::MPV_FORMAT)
+            .and_then(|value| Property::from_owned(OwnedProperty::new(name, value)).data::<T>())
+            .ok_or(IpcError::Mpv(Error::new(mpv_error_MPV_ERROR_PROPERTY_FORMAT)))
+    }
+
+    pub fn set_property<T: Format>(&mut self, name: &str, data: T) -> std::result::Result<(), IpcError> {
+        let data = to_json(&to_node(data)?);
+        self.request(json!(["set_property", name, data])).map(|_| ())
+    }
+
+    /// Observe the property `name`. The changes are returned by
+    /// `IpcClient::wait_event` as `Event::PropertyChange(reply, ..)`, with the
+    /// data converted to `T`.
+    pub fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> std::result::Result<(), IpcError> {
+        self.observed.insert(reply, T::MPV_FORMAT);
+        let result = self.request(json!(["observe_property", reply, name]));
+        if result.is_err() {
+            self.observed.remove(&reply);
+        }
+        result.map(|_| ())
+    }
+
+    /// Undo `IpcClient::observe_property`, returns the number of properties
+    /// removed.
+    pub fn unobserve_property(&mut self, registered_reply: u64) -> std::result::Result<i32, IpcError> {
+        self.request(json!(["unobserve_property", registered_reply]))?;
+        Ok(self.observed.remove(&registered_reply).map_or(0, |_| 1))
+    }
+
+    /// Enable or disable the given event.
+    pub fn request_event(&mut self, event: EventKind, enable: bool) -> std::result::Result<(), IpcError> {
+        let command = if enable { "enable_event" } else { "disable_event" };
+        self.request(json!([command, event.to_string()])).map(|_| ())
+    }
+
+    /// Enable or disable receiving of log messages.
+    pub fn request_log_messages(&mut self, min_level: LogLevel) -> std::result::Result<(), IpcError> {
+        self.request(json!(["request_log_messages", min_level.as_str()]))
+            .map(|_| ())
+    }
+
+    /// Wait for the next event, or until the timeout expires. Like
+    /// `Handle::wait_event`, a negative timeout waits forever and a zero
+    /// timeout doesn't wait. `Event::Shutdown` is returned once the connection
+    /// is closed.
+    pub fn wait_event(&mut self, timeout: f64) -> Event {
+        if let Some(event) = self.events.pop_front() {
+            return event;
+        }
+        if self.closed {
+            return Event::Shutdown;
+        }
+
+        let deadline = (timeout >= 0.).then(|| Instant::now() + Duration::from_secs_f64(timeout));
+        loop {
+            match self.read_message(deadline) {
+                Ok(Some(message)) if message.contains_key("event") => return self.parse_event(&message),
+                Ok(Some(_)) => {}
+                Ok(None) => return Event::None,
+                Err(IpcError::InvalidReply(_)) => {}
+                Err(_) => {
+                    self.closed = true;
+                    return Event::Shutdown;
+                }
+            }
+        }
+    }
+
+    /// Send a command and wait for its reply. The events received meanwhile
+    /// are queued.
+    fn request(&mut self, command: Json) -> std::result::Result<Json, IpcError> {
+        if self.closed {
+            return Err(IpcError::Closed);
+        }
+
+        let request_id = self.next_request_id;
+        self.next_request_id += 1;
+        let mut line = serde_json::to_vec(&json!({ "command": command, "request_id": request_id }))
+            .map_err(|e| IpcError::InvalidReply(e.to_string()))?;
+        line.push(b'\n');
+        self.stream.write_all(&line)?;
+
+        loop {
+            let Some(mut message) = self.read_message(None)? else {
+                continue;
+            };
+            if message.contains_key("event") {
+                let event = self.parse_event(&message);
+                self.events.push_back(event);
+            } else if message.get("request_id").and_then(Json::as_i64) == Some(request_id) {
+                return match str_field(&message, "error") {
+                    "success" => Ok(message.remove("data").unwrap_or(Json::Null)),
+                    error => Err(IpcError::Mpv(Error::from_message(error))),
+                };
+            }
+        }
+    }
+
+    /// Read the next message, or `None` if `deadline` passed first. Without
+    /// deadline, wait until a message is received.
+    fn read_message(&mut self, deadline: Option<Instant>) -> std::result::Result<Option<Map<String, Json>>, IpcError> {
+        let mut waited = false;
+        loop {
+            if let Some(end) = self.buffer.iter().position(|&byte| byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                if line.iter().all(u8::is_ascii_whitespace) {
+                    continue;
+                }
+                return serde_json::from_slice(&line)
+                    .map(Some)
+                    .map_err(|e| IpcError::InvalidReply(e.to_string()));
+            }
+
+            let timeout = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() && waited {
+                        return Ok(None);
+                    }
+                    Some(remaining.max(MIN_READ_TIMEOUT))
+                }
+                None => None,
+            };
+            self.stream.set_read_timeout(timeout)?;
+            waited = true;
+
+            let mut chunk = [0; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    self.closed = true;
+                    return Err(IpcError::Closed);
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(None),
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// Convert an event message, unknown events become `Event::None`.
+    fn parse_event(&self, message: &Map<String, Json>) -> Event {
+        match str_field(message, "event") {
+            "shutdown" => Event::Shutdown,
+            "log-message" => Event::LogMessage(LogMessage::new(
+                str_field(message, "prefix"),
+                LogLevel::from_name(str_field(message, "level")).unwrap_or(LogLevel::Info),
+                str_field(message, "text"),
+            )),
+            "start-file" => Event::StartFile(StartFile::new(i64_field(message, "playlist_entry_id"))),
+            "end-file" => Event::EndFile(EndFile::new(
+                EndFileReason::from_name(str_field(message, "reason")).unwrap_or(EndFileReason::Eof),
+                message
+                    .get("file_error")
+                    .and_then(Json::as_str)
+                    .map(Error::from_message),
+                i64_field(message, "playlist_entry_id"),
+            )),
+            "file-loaded" => Event::FileLoaded,
+            "client-message" => {
+                let args: Vec<&str> = message
+                    .get("args")
+                    .and_then(Json::as_array)
+                    .map(|args| args.iter().filter_map(Json::as_str).collect())
+                    .unwrap_or_default();
+                Event::ClientMessage(ClientMessage::new(&args))
+            }
+            "video-reconfig" => Event::VideoReconfig,
+            "audio-reconfig" => Event::AudioReconfig,
+            "seek" => Event::Seek,
+            "playback-restart" => Event::PlaybackRestart,
+            "property-change" => {
+                let reply = message.get("id").and_then(Json::as_u64).unwrap_or_default();
+                let format = self.observed.get(&reply).copied().unwrap_or(mpv_format_MPV_FORMAT_NODE);
+                let value = match message.get("data").map(from_json) {
+                    None | Some(Node::None) => Value::None,
+                    Some(data) => Value::convert(&data, format).unwrap_or(Value::None),
+                };
+                let property = OwnedProperty::new(str_field(message, "name"), value);
+                Event::PropertyChange(reply, Property::from_owned(property))
+            }
+            _ => Event::None,
+        }
+    }
+}
+
+impl Player for IpcClient {
+    fn command<I, S>(&mut self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Ok(IpcClient::command(self, args)?)
+    }
+
+    fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Ok(IpcClient::command_ret(self, args)?)
+    }
+
+    fn command_node(&mut self, args: Node) -> Result<Node> {
+        Ok(IpcClient::command_node(self, args)?)
+    }
+
+    fn get_property<T: Format>(&mut self, name: &str) -> Result<T> {
+        Ok(IpcClient::get_property(self, name)?)
+    }
+
+    fn set_property<T: Format>(&mut self, name: &str, data: T) -> Result<()> {
+        Ok(IpcClient::set_property(self, name, data)?)
+    }
+
+    fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> Result<()> {
+        Ok(IpcClient::observe_property::<T>(self, reply, name)?)
+    }
+
+    fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32> {
+        Ok(IpcClient::unobserve_property(self, registered_reply)?)
+    }
+
+    fn hook_add(&mut self, _reply: u64, _name: &str, _priority: i32) -> Result<()> {
+        Err(Error::new(mpv_error_MPV_ERROR_NOT_IMPLEMENTED))
+    }
+
+    fn hook_continue(&mut self, _id: u64) -> Result<()> {
+        Err(Error::new(mpv_error_MPV_ERROR_NOT_IMPLEMENTED))
+    }
+
+    fn wait_event(&mut self, timeout: f64) -> Event {
+        IpcClient::wait_event(self, timeout)
+    }
+}
+
+type Method = Arc<Mutex<dyn FnMut(&mut Handle, Json) -> std::result::Result<Json, String> + Send>>;
+
+type Accept = Box<dyn Fn(&UnixStream) -> bool + Send + Sync>;
+
+/// The socket of a connection, to close it, and the thread serving it.
+type ConnectionThread = (UnixStream, JoinHandle<()>);
+
+/// The commands of the protocol that are not mpv commands.
+const BUILTINS: [&str; 13] = [
+    "client_name",
+    "get_time_us",
+    "get_version",
+    "get_property",
+    "get_property_string",
+    "set_property",
+    "set_property_string",
+    "observe_property",
+    "observe_property_string",
+    "unobserve_property",
+    "request_log_messages",
+    "enable_event",
+    "disable_event",
+];
+
+/// A server of the JSON IPC protocol on top of a `Handle`, like mpv's
+/// `--input-ipc-server`, see the module documentation.
+pub struct IpcServer {
+    path: PathBuf,
+    methods: HashMap<String, Method>,
+    accept: Option<Accept>,
+}
+
+/// A running `IpcServer`. The server is stopped, its connections closed and
+/// its socket removed when dropped.
+pub struct IpcListener {
+    path: PathBuf,
+    stopped: Arc<AtomicBool>,
+    connections: Arc<Mutex<Vec<ConnectionThread>>>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// The configuration shared by the connections.
+struct Shared {
+    methods: HashMap<String, Method>,
+    accept: Option<Accept>,
+}
+
+/// Interrupts `Handle::wait_event` from the reader thread of a connection.
+struct Waker(usize);
+
+/// A connection to an `IpcServer`, served by its own client.
+struct Connection {
+    client: Client,
+    writer: UnixStream,
+    shared: Arc<Shared>,
+    closed: bool,
+}
+
+fn invalid_parameter() -> Error {
+    Error::new(mpv_error_MPV_ERROR_INVALID_PARAMETER)
+}
+
+fn str_arg(args: &[Json], index: usize) -> Result<&str> {
+    args.get(index).and_then(Json::as_str).ok_or_else(invalid_parameter)
+}
+
+fn u64_arg(args: &[Json], index: usize) -> Result<u64> {
+    args.get(index).and_then(Json::as_u64).ok_or_else(invalid_parameter)
+}
+
+/// The message sent to the clients for `event`, if any.
+fn event_message(event: &Event) -> Option<Json> {
+    let mut message = Map::new();
+    message.insert(String::from("event"), json!(event.to_string()));
+    match event {
+        Event::None
+        | Event::GetPropertyReply(..)
+        | Event::SetPropertyReply(..)
+        | Event::CommandReply(..)
+        | Event::Hook(..) => return None,
+        Event::LogMessage(log) => {
+            message.insert(String::from("prefix"), json!(log.prefix()));
+            message.insert(String::from("level"), json!(log.level().as_str()));
+            message.insert(String::from("text"), json!(log.text()));
+        }
+        Event::StartFile(start_file) => {
+            message.insert(String::from("playlist_entry_id"), json!(start_file.playlist_entry_id()));
+        }
+        Event::EndFile(end_file) => {
+            message.insert(String::from("reason"), json!(end_file.reason().to_string()));
+            message.insert(String::from("playlist_entry_id"), json!(end_file.playlist_entry_id()));
+            if let Some(error) = end_file.error() {
+                message.insert(String::from("file_error"), json!(error.message()));
+            }
+        }
+        Event::ClientMessage(client_message) => {
+            message.insert(String::from("args"), json!(client_message.args()));
+        }
+        Event::PropertyChange(reply, property) => {
+            message.insert(String::from("id"), json!(reply));
+            message.insert(String::from("name"), json!(property.name()));
+            if let Some(node) = property.data::<Node>() {
+                message.insert(String::from("data"), to_json(&node));
+            } else if let Some(string) = property.data::<String>() {
+                message.insert(String::from("data"), json!(string));
+            }
+        }
+        _ => {}
+    }
+    Some(Json::Object(message))
+}
+
+impl IpcServer {
+    /// A server listening on the Unix socket `path`. Like mpv, an existing
+    /// file at `path` is replaced.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            methods: HashMap::new(),
+            accept: None,
+        }
+    }
+
+    /// Add the custom command `name`. It receives the other arguments of the
+    /// command as a JSON array, and its result or error message is sent back
+    /// as the reply. Custom commands take precedence over mpv's.
+    pub fn method<F>(mut self, name: &str, method: F) -> Self
+    where
+        F: FnMut(&mut Handle, Json) -> std::result::Result<Json, String> + Send + 'static,
+    {
+        self.methods.insert(name.to_owned(), Arc::new(Mutex::new(method)));
+        self
+    }
+
+    /// Only serve the connections for which `accept` returns `true`, e.g. after
+    /// checking the credentials of the peer. The others are closed right away.
+    pub fn accept<F>(mut self, accept: F) -> Self
+    where
+        F: Fn(&UnixStream) -> bool + Send + Sync + 'static,
+    {
+        self.accept = Some(Box::new(accept));
+        self
+    }
+
+    /// Start serving in background threads. Each connection gets its own
+    /// client, created from `handle`, so it receives its own events. The
+    /// server stops when the player shuts down.
+    pub fn start(self, handle: &mut Handle) -> io::Result<IpcListener> {
+        let client = handle
+            .create_client("ipc-server")
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        let _ = fs::remove_file(&self.path);
+        let listener = UnixListener::bind(&self.path)?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let shared = Arc::new(Shared {
+            methods: self.methods,
+            accept: self.accept,
+        });
+        let (sender, receiver) = mpsc::channel();
+
+        let server = {
+            let stopped = stopped.clone();
+            let shared = shared.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stopped.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else {
+                        continue;
+                    };
+                    if shared.accept.as_ref().is_some_and(|accept| !accept(&stream)) {
+                        continue;
+                    }
+                    if sender.send(stream).is_err() {
+                        break;
+                    }
+                }
+            })
+        };
+        let player = {
+            let path = self.path.clone();
+            let stopped = stopped.clone();
+            let connections = connections.clone();
+            thread::spawn(move || run_player(client, receiver, &shared, &path, &stopped, &connections))
+        };
+
+        Ok(IpcListener {
+            path: self.path,
+            stopped,
+            connections,
+            threads: vec![server, player],
+        })
+    }
+}
+
+/// Create the clients of the accepted connections and serve them, until the
+/// server is stopped or the player shuts down. The client is dropped then, so
+/// that it doesn't keep the player alive.
+fn run_player(
+    mut client: Client,
+    streams: Receiver<UnixStream>,
+    shared: &Arc<Shared>,
+    path: &Path,
+    stopped: &AtomicBool,
+    connections: &Mutex<Vec<ConnectionThread>>,
+) {
+    let mut count = 0;
+    while !stopped.load(Ordering::SeqCst) {
+        while let Ok(stream) = streams.try_recv() {
+            let (Ok(connection_client), Ok(writer)) =
+                (client.create_client(format!("ipc-{}", count)), stream.try_clone())
+            else {
+                continue;
+            };
+            count += 1;
+            let connection = Connection {
+                client: connection_client,
+                writer,
+                shared: shared.clone(),
+                closed: false,
+            };
+            let thread = thread::spawn(move || connection.serve());
+
+            let mut connections = connections.lock().unwrap_or_else(|e| e.into_inner());
+            connections.retain(|(_, thread): &ConnectionThread| !thread.is_finished());
+            connections.push((stream, thread));
+        }
+
+        if let Event::Shutdown = client.wait_event(POLL_INTERVAL.as_secs_f64()) {
+            break;
+        }
+    }
+    if !stopped.swap(true, Ordering::SeqCst) {
+        // Unblock the accept loop.
+        let _ = UnixStream::connect(path);
+    }
+}
+
+impl IpcListener {
+    /// The path of the socket.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for IpcListener {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Unblock the accept loop.
+        let _ = UnixStream::connect(&self.path);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+
+        let connections = std::mem::take(&mut *self.connections.lock().unwrap_or_else(|e| e.into_inner()));
+        for (stream, thread) in connections {
+            let _ = stream.shutdown(Shutdown::Both);
+            let _ = thread.join();
+        }
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl Waker {
+    fn wake(&self) {
+        unsafe { mpv_wakeup(self.0 as *mut mpv_handle) }
+    }
+}
+
+impl Connection {
+    /// Serve the requests until the connection or the player is closed.
+    fn serve(mut self) {
+        let Ok(reader) = self.writer.try_clone() else {
+            return;
+        };
+        // The reader thread is joined before the client is destroyed.
+        let waker = Waker(unsafe { self.client.as_mut_ptr() } as usize);
+        let (sender, receiver) = mpsc::channel();
+        let reader = thread::spawn(move || {
+            for line in BufReader::new(reader).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+                waker.wake();
+            }
+            drop(sender);
+            waker.wake();
+        });
+
+        while !self.closed {
+            loop {
+                match receiver.try_recv() {
+                    Ok(line) => self.handle_line(&line),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => self.closed = true,
+                }
+                if self.closed {
+                    break;
+                }
+            }
+            if self.closed {
+                break;
+            }
+
+            match self.client.wait_event(-1.) {
+                Event::CommandReply(result, reply) => {
+                    let result = result.map(|command| to_json(&command.result()));
+                    self.reply(json!(reply), result.map_err(|e| e.message().to_owned()));
+                }
+                event => {
+                    if let Some(message) = event_message(&event) {
+                        self.send(&message);
+                    }
+                    if let Event::Shutdown = event {
+                        self.closed = true;
+                    }
+                }
+            }
+        }
+
+        let _ = self.writer.shutdown(Shutdown::Both);
+        let _ = reader.join();
+    }
+
+    fn send(&mut self, message: &Json) {
+        let mut line = message.to_string();
+        line.push('\n');
+        if self.writer.write_all(line.as_bytes()).is_err() {
+            self.closed = true;
+        }
+    }
+
+    fn reply(&mut self, request_id: Json, result: std::result::Result<Json, String>) {
+        let message = match result {
+            Ok(data) => json!({ "request_id": request_id, "error": "success", "data": data }),
+            Err(error) => json!({ "request_id": request_id, "error": error }),
+        };
+        self.send(&message);
+    }
+
+    fn handle_line(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        let request = match serde_json::from_str(line) {
+            Ok(Json::Object(request)) => request,
+            _ => return self.reply(json!(0), Err(invalid_parameter().message().to_owned())),
+        };
+        let request_id = request.get("request_id").cloned().unwrap_or(json!(0));
+        let Some(command) = request.get("command") else {
+            return self.reply(request_id, Err(invalid_parameter().message().to_owned()));
+        };
+
+        // Commands run asynchronously are answered on `Event::CommandReply`.
+        let run_async = request.get("async").and_then(Json::as_bool).unwrap_or(false);
+        if let (true, Some(reply)) = (run_async, request_id.as_u64()) {
+            if !self.is_builtin(command) {
+                if let Err(e) = self.client.command_node_async(reply, from_json(command)) {
+                    self.reply(request_id, Err(e.message().to_owned()));
+                }
+                return;
+            }
+        }
+
+        let result = self.run(command);
+        self.reply(request_id, result);
+    }
+
+    /// Whether `command` is a custom method or a command of the protocol,
+    /// rather than an mpv command.
+    fn is_builtin(&self, command: &Json) -> bool {
+        match command.get(0).and_then(Json::as_str) {
+            Some(name) => self.shared.methods.contains_key(name) || BUILTINS.contains(&name),
+            None => false,
+        }
+    }
+
+    fn run(&mut self, command: &Json) -> std::result::Result<Json, String> {
+        let args = command.as_array().map(Vec::as_slice).unwrap_or_default();
+        let name = args.first().and_then(Json::as_str);
+        if let Some(method) = name.and_then(|name| self.shared.methods.get(name)) {
+            let params = Json::Array(args[1..].to_vec());
+            return method.lock().unwrap_or_else(|e| e.into_inner())(&mut self.client, params);
+        }
+        self.run_builtin(name, args, command)
+            .map_err(|e| e.message().to_owned())
+    }
+
+    fn run_builtin(&mut self, name: Option<&str>, args: &[Json], command: &Json) -> Result<Json> {
+        let handle: &mut Handle = &mut self.client;
+        match name {
+            Some("client_name") => Ok(json!(handle.name())),
+            Some("get_time_us") => Ok(json!(unsafe { mpv_get_time_us(handle.as_mut_ptr()) })),
+            Some("get_version") => Ok(json!(unsafe { mpv_client_api_version() })),
+            Some("get_property") => handle
+                .get_property::<Node>(str_arg(args, 1)?)
+                .map(|node| to_json(&node)),
+            Some("get_property_string") => handle.get_property::<String>(str_arg(args, 1)?).map(Json::String),
+            Some("set_property") => {
+                let value = args.get(2).ok_or_else(invalid_parameter)?;
+                handle
+                    .set_property(str_arg(args, 1)?, from_json(value))
+                    .map(|()| Json::Null)
+            }
+            Some("set_property_string") => handle
+                .set_property(str_arg(args, 1)?, str_arg(args, 2)?.to_owned())
+                .map(|()| Json::Null),
+            Some("observe_property") => handle
+                .observe_property::<Node>(u64_arg(args, 1)?, str_arg(args, 2)?)
+                .map(|()| Json::Null),
+            Some("observe_property_string") => handle
+                .observe_property::<String>(u64_arg(args, 1)?, str_arg(args, 2)?)
+                .map(|()| Json::Null),
+            Some("unobserve_property") => handle.unobserve_property(u64_arg(args, 1)?).map(|_| Json::Null),
+            Some("request_log_messages") => {
+                let level = LogLevel::from_name(str_arg(args, 1)?).ok_or_else(invalid_parameter)?;
+                handle.request_log_messages(level).map(|()| Json::Null)
+            }
+            Some(name @ ("enable_event" | "disable_event")) => {
+                let enable = name == "enable_event";
+                match str_arg(args, 1)? {
+                    "all" => EventKind::ALL
+                        .into_iter()
+                        .try_for_each(|kind| handle.request_event(kind, enable)),
+                    event => {
+                        let kind = EventKind::from_name(event).ok_or_else(invalid_parameter)?;
+                        handle.request_event(kind, enable)
+                    }
+                }
+                .map(|()| Json::Null)
+            }
+            _ => handle.command_node(from_json(command)).map(|node| to_json(&node)),
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! Conversion between `Node` and JSON values.
+
+use super::Node;
+
+use serde_json::{Map, Number, Value};
+
+/// Key of the object standing for a `Node::ByteArray`, which JSON can't
+/// represent.
+const BYTES_KEY: &str = "$bytes";
+
+pub(crate) fn to_json(node: &Node) -> Value {
+    match node {
+        Node::None => Value::Null,
+        Node::String(value) => Value::String(value.clone()),
+        Node::Int(value) => Value::Number((*value).into()),
+        Node::Double(value) => Number::from_f64(*value).map_or(Value::Null, Value::Number),
+        Node::Bool(value) => Value::Bool(*value),
+        Node::ByteArray(bytes) => {
+            let bytes = bytes.iter().map(|byte| Value::Number((*byte).into())).collect();
+            Value::Object(Map::from_iter([(BYTES_KEY.to_owned(), Value::Array(bytes))]))
+        }
+        Node::Array(values) => Value::Array(values.iter().map(to_json).collect()),
+        Node::Map(map) => Value::Object(map.iter().map(|(key, value)| (key.clone(), to_json(value))).collect()),
+    }
+}
+
+pub(crate) fn from_json(value: &Value) -> Node {
+    match value {
+        Value::Null => Node::None,
+        Value::Bool(value) => Node::Bool(*value),
+        Value::Number(number) => match number.as_i64() {
+            Some(value) => Node::Int(value),
+            None => Node::Double(number.as_f64().unwrap_or_default()),
+        },
+        Value::String(value) => Node::String(value.clone()),
+        Value::Array(values) => Node::Array(values.iter().map(from_json).collect()),
+        Value::Object(map) => match map.get(BYTES_KEY) {
+            Some(Value::Array(bytes)) if map.len() == 1 => Node::ByteArray(
+                bytes
+                    .iter()
+                    .filter_map(|byte| byte.as_u64())
+                    .map(|byte| byte as u8)
+                    .collect(),
+            ),
+            _ => Node::Map(map.iter().map(|(key, value)| (key.clone(), from_json(value))).collect()),
+        },
+    }
+}
@@ -2,21 +2,59 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+mod binding;
+pub mod commands;
+mod dispatch;
 mod error;
 mod format;
+mod input;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(any(feature = "ipc", feature = "record", feature = "remote"))]
+mod json;
+#[cfg(any(feature = "log", feature = "tracing"))]
+pub mod logging;
+pub mod menu;
+mod message;
+pub mod mock;
+#[cfg(feature = "mpris")]
+pub mod mpris;
 pub mod node;
+pub mod options;
+pub mod osd;
+mod owned;
+mod pending;
+mod player;
+pub mod probe;
+pub mod prompt;
+#[cfg(feature = "record")]
+pub mod record;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+pub mod screenshot;
+pub mod subprocess;
+mod timer;
 
+pub use binding::{KeyBindingFlags, KeyEvent, KeyState};
 pub use error::{Error, Result};
 pub use format::Format;
+pub use message::{ArgsError, FromArgs};
 pub use node::Node;
+pub use pending::PendingCommand;
+pub use player::Player;
+pub use timer::Timer;
 
-use std::ffi::{c_char, c_void, CStr, CString};
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
 use std::fmt;
 use std::ops::{Deref, DerefMut};
 use std::ptr::slice_from_raw_parts_mut;
+use std::sync::{Arc, Mutex};
 
 pub use ffi::mpv_handle;
 use ffi::*;
+use owned::{OwnedClientMessage, OwnedCommandResult, OwnedHook, OwnedLogMessage, OwnedProperty, Value};
 
 /// Representation of a borrowed client context used by the client API.
 /// Every client has its own private handle.
@@ -27,6 +65,13 @@ pub struct Handle {
 /// A type representing an owned client context.
 pub struct Client(*mut mpv_handle);
 
+/// A type representing an owned weak client context, created with
+/// `Handle::create_weak_client`.
+///
+/// Unlike `Client`, it does not keep the core alive: once every other client
+/// is destroyed, the player quits and this client receives `Event::Shutdown`.
+pub struct WeakClient(*mut mpv_handle);
+
 /// An enum representing the available events that can be received by
 /// `Handle::wait_event`.
 pub enum Event {
@@ -45,8 +90,8 @@ pub enum Event {
     /// (Unlike `GetPropertyReply`, `Property` is not used.)
     SetPropertyReply(Result<()>, u64),
     /// Reply to a `Handle::command_async` or mpv_command_node_async() request.
-    /// See also `Command`.
-    CommandReply(Result<()>, u64), // TODO mpv_event_command and mpv_node
+    /// See also `CommandResult`.
+    CommandReply(Result<CommandResult>, u64),
     /// Notification before playback start of a file (before the file is loaded).
     /// See also `StartFile`.
     StartFile(StartFile),
@@ -92,6 +137,11 @@ pub enum Event {
     ///
     /// Event delivery will continue normally once this event was returned
     /// (this forces the client to empty the queue completely).
+    ///
+    /// The next calls to `Handle::wait_event` return a `PropertyChange` event
+    /// with the current value of every property observed with
+    /// `Handle::observe_property`, as changes may have been dropped.
+    /// See also `Handle::queue_overflows`.
     QueueOverflow,
     /// Triggered if a hook handler was registered with `Handle::hook_add`, and the
     /// hook is invoked. If you receive this, you must handle it, and continue
@@ -100,23 +150,86 @@ pub enum Event {
     Hook(u64, Hook),
 }
 
+/// The kind of an `Event`, without its associated data. Used to enable or
+/// disable events with `Handle::request_event`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EventKind {
+    Shutdown,
+    LogMessage,
+    GetPropertyReply,
+    SetPropertyReply,
+    CommandReply,
+    StartFile,
+    EndFile,
+    FileLoaded,
+    ClientMessage,
+    VideoReconfig,
+    AudioReconfig,
+    Seek,
+    PlaybackRestart,
+    PropertyChange,
+    QueueOverflow,
+    Hook,
+}
+
+/// Minimum level of the log messages requested with
+/// `Handle::request_log_messages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LogLevel {
+    /// Disable all log messages.
+    None,
+    /// Critical/aborting errors.
+    Fatal,
+    /// Simple errors.
+    Error,
+    /// Possible problems.
+    Warn,
+    /// Informational message.
+    Info,
+    /// Noisy informational message.
+    V,
+    /// Very noisy technical information.
+    Debug,
+    /// Extremely noisy.
+    Trace,
+}
+
+/// Why a file ended, see `EndFile::reason`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndFileReason {
+    /// The end of file was reached.
+    Eof,
+    /// Playback was stopped by an external action (e.g. playlist controls).
+    Stop,
+    /// Playback was stopped by the quit command or player shutdown.
+    Quit,
+    /// Some kind of error happened that lead to playback abort, see
+    /// `EndFile::error`.
+    Error,
+    /// The file was a playlist or similar, and was replaced by its entries.
+    Redirect,
+}
+
 /// Data associated with `Event::GetPropertyReply` and `Event::PropertyChange`.
-pub struct Property(*const mpv_event_property);
+pub struct Property(*const mpv_event_property, Option<Box<OwnedProperty>>);
 
 /// Data associated with `Event::LogMessage`.
-pub struct LogMessage(*const mpv_event_log_message);
+pub struct LogMessage(*const mpv_event_log_message, Option<Box<OwnedLogMessage>>);
+
+/// Data associated with a successful `Event::CommandReply`.
+pub struct CommandResult(*const mpv_event_command, Option<Box<OwnedCommandResult>>);
 
 /// Data associated with `Event::StartFile`.
-pub struct StartFile(*const mpv_event_start_file);
+pub struct StartFile(*const mpv_event_start_file, Option<Box<mpv_event_start_file>>);
 
 /// Data associated with `Event::EndFile`.
-pub struct EndFile(*const mpv_event_end_file);
+pub struct EndFile(*const mpv_event_end_file, Option<Box<mpv_event_end_file>>);
 
 /// Data associated with `Event::ClientMessage`.
-pub struct ClientMessage(*const mpv_event_client_message);
+pub struct ClientMessage(*const mpv_event_client_message, Option<Box<OwnedClientMessage>>);
 
 /// Data associated with `Event::Hook`.
-pub struct Hook(*const mpv_event_hook);
+pub struct Hook(*const mpv_event_hook, Option<Box<OwnedHook>>);
 
 macro_rules! result {
     ($f:expr) => {
@@ -188,13 +301,15 @@ impl Handle {
         }
     }
 
-    pub fn create_weak_client(&mut self, name: impl AsRef<str>) -> Result<Client> {
+    /// This is the same as `Handle::create_client`, but the created client is
+    /// weak, see `WeakClient`.
+    pub fn create_weak_client(&mut self, name: impl AsRef<str>) -> Result<WeakClient> {
         let name = CString::new(name.as_ref())?;
         let handle = unsafe { mpv_create_weak_client(self.as_mut_ptr(), name.as_ptr()) };
         if handle.is_null() {
             Err(Error::new(mpv_error_MPV_ERROR_NOMEM))
         } else {
-            Ok(Client(handle))
+            Ok(WeakClient(handle))
         }
     }
 
@@ -220,8 +335,104 @@ impl Handle {
     ///
     /// As long as the timeout is 0, this is safe to be called from mpv render API
     /// threads.
+    ///
+    /// The callbacks of the timers added with `Handle::add_timeout` and
+    /// `Handle::add_periodic_timer`, and of the key bindings added with
+    /// `Handle::add_key_binding`, are run while waiting. The events handled
+    /// by these callbacks are not returned.
     pub fn wait_event(&mut self, timeout: f64) -> Event {
-        unsafe { Event::from_ptr(mpv_wait_event(self.as_mut_ptr(), timeout)) }
+        let handle = unsafe { self.as_ptr() };
+
+        // The resynchronized changes go to their callbacks like the others.
+        while let Some(observation) = dispatch::next_resync(handle) {
+            let event = Event::PropertyChange(observation.reply, self.read_observed(&observation));
+            if !self.dispatch_event(&event) {
+                return event;
+            }
+        }
+
+        let deadline = (timeout > 0.).then(|| self.get_time_us().saturating_add((timeout * 1e6) as i64));
+
+        let event = loop {
+            let next_timer = self.run_timers();
+            let now = self.get_time_us();
+            let until = match (deadline, next_timer) {
+                (Some(deadline), Some(timer)) => Some(deadline.min(timer)),
+                (deadline, timer) => deadline.or(timer),
+            };
+            let wait = match until {
+                _ if timeout == 0. => 0.,
+                Some(until) => (until - now).max(0) as f64 / 1e6,
+                None => -1.,
+            };
+
+            let event = unsafe { Event::from_ptr(mpv_wait_event(self.as_mut_ptr(), wait)) };
+            let handled = self.dispatch_event(&event);
+
+            // Keep waiting if the event was handled or only a timer expired.
+            let timer_expired = next_timer.is_some_and(|timer| timer <= self.get_time_us());
+            let timed_out = timeout == 0. || deadline.is_some_and(|deadline| deadline <= self.get_time_us());
+            match event {
+                _ if handled && timed_out => break Event::None,
+                _ if handled => continue,
+                Event::None if timer_expired && !timed_out => continue,
+                event => break event,
+            }
+        };
+
+        match event {
+            Event::QueueOverflow => dispatch::overflow(handle),
+            Event::Shutdown => dispatch::remove(handle),
+            _ => {}
+        }
+        event
+    }
+
+    /// Run the callbacks registered for the event, return whether it was handled.
+    fn dispatch_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::ClientMessage(message) => {
+                self.dispatch_key_binding(message) || self.dispatch_script_message(message)
+            }
+            Event::PropertyChange(reply, property) => {
+                let Some(callback) = dispatch::property_callback(unsafe { self.as_ptr() }, *reply) else {
+                    return false;
+                };
+                callback.lock().unwrap_or_else(|e| e.into_inner())(self, property);
+                true
+            }
+            Event::CommandReply(result, reply) => self.dispatch_command_reply(result, *reply),
+            _ => false,
+        }
+    }
+
+    /// Print a warning on the terminal, prefixed with the client name.
+    pub(crate) fn print_warning(&mut self, message: impl fmt::Display) {
+        let text = format!("[{}] WARN: {}", self.name(), message);
+        let _ = self.command(["print-text", &text]);
+    }
+
+    /// Return how many times `Event::QueueOverflow` was received on this handle.
+    ///
+    /// libmpv doesn't tell how many events were dropped, only that at least one
+    /// was dropped each time.
+    pub fn queue_overflows(&self) -> u64 {
+        dispatch::overflows(unsafe { self.as_ptr() })
+    }
+
+    /// Read an observed property to synthesize its `Event::PropertyChange`.
+    /// An unavailable property is reported without data, like mpv does.
+    fn read_observed(&mut self, observation: &dispatch::Observation) -> Property {
+        let name = &observation.name;
+        let value = match observation.format {
+            mpv_format_MPV_FORMAT_STRING => self.get_property::<String>(name).map(Value::string),
+            mpv_format_MPV_FORMAT_FLAG => self.get_property::<bool>(name).map(|v| Value::Flag(v as c_int)),
+            mpv_format_MPV_FORMAT_INT64 => self.get_property::<i64>(name).map(Value::Int64),
+            mpv_format_MPV_FORMAT_DOUBLE => self.get_property::<f64>(name).map(Value::Double),
+            mpv_format_MPV_FORMAT_NODE => self.get_property::<Node>(name).map(|v| Value::node(&v)),
+            _ => Ok(Value::None),
+        };
+        Property::from_owned(OwnedProperty::new(name, value.unwrap_or(Value::None)))
     }
 
     /// Return the name of this client handle. Every client has its own unique
@@ -264,6 +475,37 @@ impl Handle {
         unsafe { result!(mpv_command(self.as_mut_ptr(), raw_args.as_mut_ptr())) }
     }
 
+    /// Same as `Handle::command`, but return the result of the command as a
+    /// `Node`. Commands without result return `Node::None`.
+    pub fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<CString> = args.into_iter().map(|s| CString::new(s.as_ref()).unwrap()).collect();
+        let mut raw_args: Vec<*const c_char> = args.iter().map(|s| s.as_ptr()).collect();
+        raw_args.push(std::ptr::null()); // Adding null at the end
+        let handle = unsafe { self.as_mut_ptr() };
+        let raw_args = raw_args.as_mut_ptr();
+        Node::from_mpv(|data| unsafe { result!(mpv_command_ret(handle, raw_args, data as *mut mpv_node)) })
+    }
+
+    /// Same as `Handle::command`, but allows passing structured data in any
+    /// format. In particular, calling `Handle::command` is exactly like calling
+    /// `Handle::command_node` with the format set to `Node::Array`, and every
+    /// arg passed as `Node::String`.
+    ///
+    /// Does not use OSD and string expansion by default. `args` can also be a
+    /// `Node::Map`, where the `name` key is the command name and the other keys
+    /// are named arguments.
+    pub fn command_node(&mut self, args: Node) -> Result<Node> {
+        let handle = unsafe { self.as_mut_ptr() };
+        let args = node::to_mpv_node(&args);
+        let result = Node::from_mpv(|data| unsafe { result!(mpv_command_node(handle, args, data as *mut mpv_node)) });
+        unsafe { node::free_mpv_node(args) };
+        result
+    }
+
     /// Same as `Handle::command`, but run the command asynchronously.
     ///
     /// Commands are executed asynchronously. You will receive a
@@ -288,6 +530,28 @@ impl Handle {
         unsafe { result!(mpv_command_async(self.as_mut_ptr(), reply, raw_args.as_mut_ptr())) }
     }
 
+    /// Same as `Handle::command_node`, but run the command asynchronously, like
+    /// `Handle::command_async`. The result is the one of the `CommandReply`
+    /// event, see `CommandResult::result`.
+    pub fn command_node_async(&mut self, reply: u64, args: Node) -> Result<()> {
+        let handle = unsafe { self.as_mut_ptr() };
+        let args = node::to_mpv_node(&args);
+        let result = unsafe { result!(mpv_command_node_async(handle, reply, args)) };
+        unsafe { node::free_mpv_node(args) };
+        result
+    }
+
+    /// Signal to all async requests with the matching `reply` that they should
+    /// be terminated, like `mp.abort_async_command` in Lua. This happens
+    /// asynchronously, and the `CommandReply` event is still sent, usually
+    /// with an error.
+    ///
+    /// Only some commands can be aborted, e.g. `subprocess` or network
+    /// `loadfile`; others simply run to completion.
+    pub fn abort_async_command(&mut self, reply: u64) {
+        unsafe { mpv_abort_async_command(self.as_mut_ptr(), reply) }
+    }
+
     pub fn set_property<T: Format>(&mut self, name: impl AsRef<str>, data: T) -> Result<()> {
         let name = CString::new(name.as_ref())?;
         let handle = unsafe { self.as_mut_ptr() };
@@ -307,15 +571,44 @@ impl Handle {
     }
 
     pub fn observe_property<T: Format>(&mut self, reply: u64, name: impl AsRef<str>) -> Result<()> {
-        let name = CString::new(name.as_ref())?;
+        let cname = CString::new(name.as_ref())?;
         unsafe {
             result!(mpv_observe_property(
                 self.as_mut_ptr(),
                 reply,
-                name.as_ptr(),
+                cname.as_ptr(),
                 T::MPV_FORMAT
-            ))
+            ))?;
         }
+        dispatch::observe(
+            unsafe { self.as_ptr() },
+            dispatch::Observation {
+                reply,
+                name: name.as_ref().to_owned(),
+                format: T::MPV_FORMAT,
+            },
+        );
+        Ok(())
+    }
+
+    /// Same as `Handle::observe_property`, but `callback` is called with the
+    /// property instead of returning `Event::PropertyChange`, like
+    /// `mp.observe_property` in Lua.
+    ///
+    /// The returned reply number can be passed to `Handle::unobserve_property`.
+    /// These numbers are allocated downwards from `u64::MAX`, don't use them with
+    /// `Handle::observe_property`.
+    pub fn observe_property_callback<T, F>(&mut self, name: impl AsRef<str>, callback: F) -> Result<u64>
+    where
+        T: Format,
+        F: FnMut(&mut Handle, &Property) + Send + 'static,
+    {
+        let handle = unsafe { self.as_ptr() };
+        let reply = dispatch::add_property_callback(handle, Arc::new(Mutex::new(callback)));
+        self.observe_property::<T>(reply, name).inspect_err(|_| {
+            drop(dispatch::remove_property_callback(handle, reply));
+        })?;
+        Ok(reply)
     }
 
     /// Undo `Handle::observe_property`. This will remove all observed properties for
@@ -323,6 +616,11 @@ impl Handle {
     ///
     /// Safe to be called from mpv render API threads.
     pub fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32> {
+        dispatch::unobserve(unsafe { self.as_ptr() }, registered_reply);
+        drop(dispatch::remove_property_callback(
+            unsafe { self.as_ptr() },
+            registered_reply,
+        ));
         unsafe { result_with_code!(mpv_unobserve_property(self.as_mut_ptr(), registered_reply)) }
     }
 
@@ -334,6 +632,36 @@ impl Handle {
     pub fn hook_continue(&mut self, id: u64) -> Result<()> {
         unsafe { result!(mpv_hook_continue(self.as_mut_ptr(), id)) }
     }
+
+    /// Block until all asynchronous requests are done. This affects functions like
+    /// `Handle::command_async`, which return immediately and return their result
+    /// as events.
+    ///
+    /// This is a helper, and somewhat equivalent to calling `Handle::wait_event`
+    /// in a loop until all known asynchronous requests have sent their reply as
+    /// event, except that the event queue is not emptied.
+    pub fn wait_async_requests(&mut self) {
+        unsafe { mpv_wait_async_requests(self.as_mut_ptr()) }
+    }
+
+    /// Enable or disable the given event.
+    ///
+    /// Some events are enabled by default. Some events can't be disabled.
+    ///
+    /// Safe to be called from mpv render API threads.
+    pub fn request_event(&mut self, event: EventKind, enable: bool) -> Result<()> {
+        unsafe { result!(mpv_request_event(self.as_mut_ptr(), event.into(), enable as c_int)) }
+    }
+
+    /// Enable or disable receiving of log messages. These are the messages the
+    /// command line player prints to the terminal. This call sets the minimum
+    /// required log level for a message to be received with `Event::LogMessage`.
+    ///
+    /// Pass `LogLevel::None` to disable log messages again.
+    pub fn request_log_messages(&mut self, min_level: LogLevel) -> Result<()> {
+        let min_level = CString::new(min_level.as_str())?;
+        unsafe { result!(mpv_request_log_messages(self.as_mut_ptr(), min_level.as_ptr())) }
+    }
 }
 
 impl Client {
@@ -349,10 +677,29 @@ impl Client {
     pub fn initialize(self) -> Result<Self> {
         unsafe { result!(mpv_initialize(self.0)).map(|()| self) }
     }
+
+    /// Disconnect and destroy the client, then terminate the player as if the
+    /// `quit` command was run, and wait until it is destroyed.
+    ///
+    /// Use this from an embedding application to shut down the core. Plugins
+    /// should not call this, as it also terminates mpv.
+    pub fn terminate(self) {
+        let handle = self.0;
+        std::mem::forget(self);
+        dispatch::remove(handle);
+        unsafe { mpv_terminate_destroy(handle) }
+    }
+
+    /// Disconnect and destroy the client, without terminating the player
+    /// unless this was the last strong client. This is what happens on drop.
+    pub fn detach(self) {
+        drop(self)
+    }
 }
 
 impl Drop for Client {
     fn drop(&mut self) {
+        dispatch::remove(self.0);
         unsafe { mpv_destroy(self.0) }
     }
 }
@@ -375,6 +722,31 @@ impl DerefMut for Client {
 
 unsafe impl Send for Client {}
 
+impl Drop for WeakClient {
+    fn drop(&mut self) {
+        dispatch::remove(self.0);
+        unsafe { mpv_destroy(self.0) }
+    }
+}
+
+impl Deref for WeakClient {
+    type Target = Handle;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        Handle::from_ptr(self.0)
+    }
+}
+
+impl DerefMut for WeakClient {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Handle::from_ptr(self.0)
+    }
+}
+
+unsafe impl Send for WeakClient {}
+
 impl Event {
     unsafe fn from_ptr(event: *const mpv_event) -> Event {
         match (*event).event_id {
@@ -388,9 +760,10 @@ impl Event {
             mpv_event_id_MPV_EVENT_SET_PROPERTY_REPLY => {
                 Event::SetPropertyReply(result!((*event).error), (*event).reply_userdata)
             }
-            mpv_event_id_MPV_EVENT_COMMAND_REPLY => {
-                Event::CommandReply(result!((*event).error), (*event).reply_userdata)
-            }
+            mpv_event_id_MPV_EVENT_COMMAND_REPLY => Event::CommandReply(
+                result!((*event).error).map(|()| CommandResult::from_ptr((*event).data)),
+                (*event).reply_userdata,
+            ),
             mpv_event_id_MPV_EVENT_START_FILE => Event::StartFile(StartFile::from_ptr((*event).data)),
             mpv_event_id_MPV_EVENT_END_FILE => Event::EndFile(EndFile::from_ptr((*event).data)),
             mpv_event_id_MPV_EVENT_FILE_LOADED => Event::FileLoaded,
@@ -439,23 +812,236 @@ impl fmt::Display for Event {
     }
 }
 
+impl EventKind {
+    #[cfg(any(feature = "ipc", feature = "log"))]
+    const ALL: [Self; 16] = [
+        Self::Shutdown,
+        Self::LogMessage,
+        Self::GetPropertyReply,
+        Self::SetPropertyReply,
+        Self::CommandReply,
+        Self::StartFile,
+        Self::EndFile,
+        Self::FileLoaded,
+        Self::ClientMessage,
+        Self::VideoReconfig,
+        Self::AudioReconfig,
+        Self::Seek,
+        Self::PlaybackRestart,
+        Self::PropertyChange,
+        Self::QueueOverflow,
+        Self::Hook,
+    ];
+
+    /// The kind named `name`, see `EventKind`'s `Display`.
+    #[cfg(feature = "ipc")]
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.to_string() == name)
+    }
+
+    /// Return the kind of the given event, or `None` for `Event::None`.
+    pub fn of(event: &Event) -> Option<Self> {
+        match *event {
+            Event::None => None,
+            Event::Shutdown => Some(Self::Shutdown),
+            Event::LogMessage(..) => Some(Self::LogMessage),
+            Event::GetPropertyReply(..) => Some(Self::GetPropertyReply),
+            Event::SetPropertyReply(..) => Some(Self::SetPropertyReply),
+            Event::CommandReply(..) => Some(Self::CommandReply),
+            Event::StartFile(..) => Some(Self::StartFile),
+            Event::EndFile(..) => Some(Self::EndFile),
+            Event::FileLoaded => Some(Self::FileLoaded),
+            Event::ClientMessage(..) => Some(Self::ClientMessage),
+            Event::VideoReconfig => Some(Self::VideoReconfig),
+            Event::AudioReconfig => Some(Self::AudioReconfig),
+            Event::Seek => Some(Self::Seek),
+            Event::PlaybackRestart => Some(Self::PlaybackRestart),
+            Event::PropertyChange(..) => Some(Self::PropertyChange),
+            Event::QueueOverflow => Some(Self::QueueOverflow),
+            Event::Hook(..) => Some(Self::Hook),
+        }
+    }
+}
+
+impl From<EventKind> for mpv_event_id {
+    fn from(kind: EventKind) -> Self {
+        match kind {
+            EventKind::Shutdown => mpv_event_id_MPV_EVENT_SHUTDOWN,
+            EventKind::LogMessage => mpv_event_id_MPV_EVENT_LOG_MESSAGE,
+            EventKind::GetPropertyReply => mpv_event_id_MPV_EVENT_GET_PROPERTY_REPLY,
+            EventKind::SetPropertyReply => mpv_event_id_MPV_EVENT_SET_PROPERTY_REPLY,
+            EventKind::CommandReply => mpv_event_id_MPV_EVENT_COMMAND_REPLY,
+            EventKind::StartFile => mpv_event_id_MPV_EVENT_START_FILE,
+            EventKind::EndFile => mpv_event_id_MPV_EVENT_END_FILE,
+            EventKind::FileLoaded => mpv_event_id_MPV_EVENT_FILE_LOADED,
+            EventKind::ClientMessage => mpv_event_id_MPV_EVENT_CLIENT_MESSAGE,
+            EventKind::VideoReconfig => mpv_event_id_MPV_EVENT_VIDEO_RECONFIG,
+            EventKind::AudioReconfig => mpv_event_id_MPV_EVENT_AUDIO_RECONFIG,
+            EventKind::Seek => mpv_event_id_MPV_EVENT_SEEK,
+            EventKind::PlaybackRestart => mpv_event_id_MPV_EVENT_PLAYBACK_RESTART,
+            EventKind::PropertyChange => mpv_event_id_MPV_EVENT_PROPERTY_CHANGE,
+            EventKind::QueueOverflow => mpv_event_id_MPV_EVENT_QUEUE_OVERFLOW,
+            EventKind::Hook => mpv_event_id_MPV_EVENT_HOOK,
+        }
+    }
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(unsafe {
+            CStr::from_ptr(mpv_event_name((*self).into()))
+                .to_str()
+                .unwrap_or("unknown event")
+        })
+    }
+}
+
+impl LogLevel {
+    /// The level name as understood by `mpv_request_log_messages()`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "no",
+            Self::Fatal => "fatal",
+            Self::Error => "error",
+            Self::Warn => "warn",
+            Self::Info => "info",
+            Self::V => "v",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+
+    /// The level named `name`, see `LogLevel::as_str`.
+    #[cfg(any(feature = "ipc", feature = "record"))]
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            Self::None,
+            Self::Fatal,
+            Self::Error,
+            Self::Warn,
+            Self::Info,
+            Self::V,
+            Self::Debug,
+            Self::Trace,
+        ]
+        .into_iter()
+        .find(|level| level.as_str() == name)
+    }
+
+    fn from_raw(level: mpv_log_level) -> Self {
+        match level {
+            mpv_log_level_MPV_LOG_LEVEL_FATAL => Self::Fatal,
+            mpv_log_level_MPV_LOG_LEVEL_ERROR => Self::Error,
+            mpv_log_level_MPV_LOG_LEVEL_WARN => Self::Warn,
+            mpv_log_level_MPV_LOG_LEVEL_INFO => Self::Info,
+            mpv_log_level_MPV_LOG_LEVEL_V => Self::V,
+            mpv_log_level_MPV_LOG_LEVEL_DEBUG => Self::Debug,
+            mpv_log_level_MPV_LOG_LEVEL_TRACE => Self::Trace,
+            _ => Self::None,
+        }
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<LogLevel> for mpv_log_level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::None => mpv_log_level_MPV_LOG_LEVEL_NONE,
+            LogLevel::Fatal => mpv_log_level_MPV_LOG_LEVEL_FATAL,
+            LogLevel::Error => mpv_log_level_MPV_LOG_LEVEL_ERROR,
+            LogLevel::Warn => mpv_log_level_MPV_LOG_LEVEL_WARN,
+            LogLevel::Info => mpv_log_level_MPV_LOG_LEVEL_INFO,
+            LogLevel::V => mpv_log_level_MPV_LOG_LEVEL_V,
+            LogLevel::Debug => mpv_log_level_MPV_LOG_LEVEL_DEBUG,
+            LogLevel::Trace => mpv_log_level_MPV_LOG_LEVEL_TRACE,
+        }
+    }
+}
+
+impl EndFileReason {
+    /// The reason named `name`, as displayed.
+    #[cfg(any(feature = "ipc", feature = "record"))]
+    fn from_name(name: &str) -> Option<Self> {
+        [Self::Eof, Self::Stop, Self::Quit, Self::Error, Self::Redirect]
+            .into_iter()
+            .find(|reason| reason.to_string() == name)
+    }
+
+    fn from_raw(reason: mpv_end_file_reason) -> Self {
+        match reason {
+            mpv_end_file_reason_MPV_END_FILE_REASON_STOP => Self::Stop,
+            mpv_end_file_reason_MPV_END_FILE_REASON_QUIT => Self::Quit,
+            mpv_end_file_reason_MPV_END_FILE_REASON_ERROR => Self::Error,
+            mpv_end_file_reason_MPV_END_FILE_REASON_REDIRECT => Self::Redirect,
+            _ => Self::Eof,
+        }
+    }
+}
+
+impl From<EndFileReason> for mpv_end_file_reason {
+    fn from(reason: EndFileReason) -> Self {
+        match reason {
+            EndFileReason::Eof => mpv_end_file_reason_MPV_END_FILE_REASON_EOF,
+            EndFileReason::Stop => mpv_end_file_reason_MPV_END_FILE_REASON_STOP,
+            EndFileReason::Quit => mpv_end_file_reason_MPV_END_FILE_REASON_QUIT,
+            EndFileReason::Error => mpv_end_file_reason_MPV_END_FILE_REASON_ERROR,
+            EndFileReason::Redirect => mpv_end_file_reason_MPV_END_FILE_REASON_REDIRECT,
+        }
+    }
+}
+
+impl fmt::Display for EndFileReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Eof => "eof",
+            Self::Stop => "stop",
+            Self::Quit => "quit",
+            Self::Error => "error",
+            Self::Redirect => "redirect",
+        })
+    }
+}
+
 impl Property {
     /// Wrap a raw mpv_event_property
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_property)
+        Self(ptr as *const mpv_event_property, None)
+    }
+
+    /// Wrap a mpv_event_property allocated by Rust
+    fn from_owned(property: Box<OwnedProperty>) -> Self {
+        Self(std::ptr::null(), Some(property))
+    }
+
+    /// Create a property holding `value` in its own format (see
+    /// `Property::data`), e.g. to inject events in tests.
+    pub fn new(name: &str, value: &Node) -> Self {
+        Self::from_owned(OwnedProperty::new(name, Value::from_node(value)))
+    }
+
+    fn raw(&self) -> *const mpv_event_property {
+        self.1.as_ref().map_or(self.0, |property| &property.raw)
     }
 
     /// Name of the property.
     pub fn name(&self) -> &str {
-        unsafe { CStr::from_ptr((*self.0).name) }.to_str().unwrap_or("unknown")
+        unsafe { CStr::from_ptr((*self.raw()).name) }
+            .to_str()
+            .unwrap_or("unknown")
     }
 
     pub fn data<T: Format>(&self) -> Option<T> {
         unsafe {
-            if (*self.0).format == T::MPV_FORMAT {
-                T::from_ptr((*self.0).data).ok()
+            let raw = self.raw();
+            if (*raw).format == T::MPV_FORMAT {
+                T::from_ptr((*raw).data).ok()
             } else {
                 None
             }
@@ -469,18 +1055,81 @@ impl fmt::Display for Property {
     }
 }
 
+impl CommandResult {
+    /// Wrap a raw mpv_event_command
+    fn from_ptr(ptr: *const c_void) -> Self {
+        Self(ptr as *const mpv_event_command, None)
+    }
+
+    /// Create the data of a command reply, e.g. to inject events in tests.
+    pub fn new(result: &Node) -> Self {
+        Self(std::ptr::null(), Some(OwnedCommandResult::new(result)))
+    }
+
+    fn raw(&self) -> *const mpv_event_command {
+        self.1.as_ref().map_or(self.0, |command| &command.raw)
+    }
+
+    /// The result of the command, as returned by `Handle::command_node`.
+    /// `Node::None` if the command failed or returns nothing.
+    pub fn result(&self) -> Node {
+        let raw = self.raw();
+        if raw.is_null() {
+            return Node::None;
+        }
+        unsafe { Node::from_ptr(&(*raw).result as *const mpv_node as *const c_void) }.unwrap_or_default()
+    }
+}
+
 impl LogMessage {
     /// Wrap a raw mpv_event_log_message
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_log_message)
+        Self(ptr as *const mpv_event_log_message, None)
+    }
+
+    /// Create a log message, e.g. to inject events in tests. A newline is
+    /// appended to `text` if missing.
+    pub fn new(prefix: &str, level: LogLevel, text: &str) -> Self {
+        let text = if text.ends_with('\n') {
+            text.to_owned()
+        } else {
+            format!("{}\n", text)
+        };
+        let message = OwnedLogMessage::new(prefix, level.as_str(), &text, level.into());
+        Self(std::ptr::null(), Some(message))
+    }
+
+    fn raw(&self) -> *const mpv_event_log_message {
+        self.1.as_ref().map_or(self.0, |message| &message.raw)
+    }
+
+    /// The module prefix, identifies the sender of the message. As a special
+    /// case, if the message buffer overflows, this will be set to the string
+    /// "overflow" (which doesn't appear as prefix otherwise), and the text
+    /// field will contain an informative message.
+    pub fn prefix(&self) -> &str {
+        unsafe { CStr::from_ptr((*self.raw()).prefix) }
+            .to_str()
+            .unwrap_or("unknown")
+    }
+
+    /// The log level of the message.
+    pub fn level(&self) -> LogLevel {
+        LogLevel::from_raw(unsafe { (*self.raw()).log_level })
+    }
+
+    /// The log message. It consists of 1 line of text, and is terminated with a
+    /// newline character.
+    pub fn text(&self) -> &str {
+        unsafe { CStr::from_ptr((*self.raw()).text) }.to_str().unwrap_or("")
     }
 }
 
 impl fmt::Display for LogMessage {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str("log message")
+        write!(f, "[{}] {}", self.prefix(), self.text().trim_end())
     }
 }
 
@@ -489,12 +1138,24 @@ impl StartFile {
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_start_file)
+        Self(ptr as *const mpv_event_start_file, None)
+    }
+
+    /// Create the data of a start file event, e.g. to inject events in tests.
+    pub fn new(playlist_entry_id: i64) -> Self {
+        Self(
+            std::ptr::null(),
+            Some(Box::new(mpv_event_start_file { playlist_entry_id })),
+        )
+    }
+
+    fn raw(&self) -> *const mpv_event_start_file {
+        self.1.as_deref().map_or(self.0, |raw| raw)
     }
 
     /// Playlist entry ID of the file being loaded now.
     pub fn playlist_entry_id(&self) -> i64 {
-        unsafe { (*self.0).playlist_entry_id }
+        unsafe { (*self.raw()).playlist_entry_id }
     }
 }
 
@@ -509,7 +1170,54 @@ impl EndFile {
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_end_file)
+        Self(ptr as *const mpv_event_end_file, None)
+    }
+
+    /// Create the data of an end file event, e.g. to inject events in tests.
+    /// `error` is only used with `EndFileReason::Error`.
+    pub fn new(reason: EndFileReason, error: Option<Error>, playlist_entry_id: i64) -> Self {
+        let raw = mpv_event_end_file {
+            reason: reason.into(),
+            error: error.map_or(mpv_error_MPV_ERROR_SUCCESS, |e| e.code()),
+            playlist_entry_id,
+            playlist_insert_id: 0,
+            playlist_insert_num_entries: 0,
+        };
+        Self(std::ptr::null(), Some(Box::new(raw)))
+    }
+
+    fn raw(&self) -> *const mpv_event_end_file {
+        self.1.as_deref().map_or(self.0, |raw| raw)
+    }
+
+    /// Why the file ended.
+    pub fn reason(&self) -> EndFileReason {
+        EndFileReason::from_raw(unsafe { (*self.raw()).reason })
+    }
+
+    /// The error that ended playback, with `EndFileReason::Error`.
+    pub fn error(&self) -> Option<Error> {
+        match unsafe { (*self.raw()).error } {
+            mpv_error_MPV_ERROR_SUCCESS => None,
+            e => Some(Error::new(e)),
+        }
+    }
+
+    /// Playlist entry ID of the file that was being played or loaded.
+    pub fn playlist_entry_id(&self) -> i64 {
+        unsafe { (*self.raw()).playlist_entry_id }
+    }
+
+    /// With `EndFileReason::Redirect`, the playlist entry ID of the first of
+    /// the entries that replaced the file.
+    pub fn playlist_insert_id(&self) -> i64 {
+        unsafe { (*self.raw()).playlist_insert_id }
+    }
+
+    /// With `EndFileReason::Redirect`, the number of entries that replaced the
+    /// file.
+    pub fn playlist_insert_num_entries(&self) -> i32 {
+        unsafe { (*self.raw()).playlist_insert_num_entries as i32 }
     }
 }
 
@@ -524,15 +1232,37 @@ impl ClientMessage {
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_client_message)
+        Self(ptr as *const mpv_event_client_message, None)
+    }
+
+    /// Create a client message, e.g. to inject events in tests.
+    pub fn new<S: AsRef<str>>(args: &[S]) -> Self {
+        Self(std::ptr::null(), Some(OwnedClientMessage::new(args)))
+    }
+
+    fn raw(&self) -> *const mpv_event_client_message {
+        self.1.as_ref().map_or(self.0, |message| &message.raw)
+    }
+
+    /// Arguments of the message. Arguments that are not valid UTF-8 are replaced
+    /// by an empty string, see `ClientMessage::try_args`.
+    pub fn args(&self) -> Vec<&str> {
+        self.raw_args().map(|arg| arg.to_str().unwrap_or("")).collect()
     }
 
-    pub fn args<'a>(&self) -> Vec<&'a str> {
+    /// Arguments of the message, or an error if one of them is not valid UTF-8.
+    pub fn try_args(&self) -> Result<Vec<&str>> {
+        self.raw_args().map(|arg| Ok(arg.to_str()?)).collect()
+    }
+
+    fn raw_args(&self) -> impl Iterator<Item = &CStr> {
         unsafe {
-            let args = std::slice::from_raw_parts((*self.0).args, (*self.0).num_args as usize);
-            args.into_iter()
-                .map(|arg| CStr::from_ptr(*arg).to_str().unwrap())
-                .collect()
+            let raw = self.raw();
+            let args: &[*const c_char] = match (*raw).num_args {
+                0 => &[],
+                n => std::slice::from_raw_parts((*raw).args, n as usize),
+            };
+            args.iter().map(|arg| CStr::from_ptr(*arg))
         }
     }
 }
@@ -548,17 +1278,26 @@ impl Hook {
     /// The pointer must not be null
     fn from_ptr(ptr: *const c_void) -> Self {
         assert!(!ptr.is_null());
-        Self(ptr as *const mpv_event_hook)
+        Self(ptr as *const mpv_event_hook, None)
+    }
+
+    /// Create the data of a hook event, e.g. to inject events in tests.
+    pub fn new(name: &str, id: u64) -> Self {
+        Self(std::ptr::null(), Some(OwnedHook::new(name, id)))
+    }
+
+    fn raw(&self) -> *const mpv_event_hook {
+        self.1.as_ref().map_or(self.0, |hook| &hook.raw)
     }
 
     /// The hook name as passed to `Handle::hook_add`.
     pub fn name(&self) -> &str {
-        unsafe { CStr::from_ptr((*self.0).name).to_str().unwrap_or("unknown") }
+        unsafe { CStr::from_ptr((*self.raw()).name).to_str().unwrap_or("unknown") }
     }
 
     /// Internal ID that must be passed to `Handle::hook_continue`.
     pub fn id(&self) -> u64 {
-        unsafe { (*self.0).id }
+        unsafe { (*self.raw()).id }
     }
 }
 
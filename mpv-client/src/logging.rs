@@ -0,0 +1,178 @@
+//! Bridge between mpv's log messages and the `log` and `tracing` crates.
+//!
+//! Messages received with `Event::LogMessage` can be passed to `forward`,
+//! which emits them with the mpv module prefix as target (as a `prefix` field
+//! for `tracing`, which only supports static targets).
+//!
+//! With the `log` feature, `MpvLogger` does the opposite and prints the records
+//! of the plugin into mpv's terminal and log file, prefixed with the client name.
+
+use super::{Handle, LogLevel, LogMessage, Result};
+
+use std::cell::Cell;
+
+#[cfg(feature = "log")]
+use super::{dispatch, Client, EventKind};
+#[cfg(feature = "log")]
+use std::sync::{Arc, Mutex, MutexGuard};
+
+thread_local! {
+    // Set while a mpv message is being forwarded, so `MpvLogger` doesn't send it
+    // back to mpv (which would log it again, forever).
+    static FORWARDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Request log messages from mpv, at the most verbose level enabled in `log`
+/// and `tracing`.
+pub fn subscribe(handle: &mut Handle) -> Result<()> {
+    handle.request_log_messages(max_level())
+}
+
+/// Emit a mpv log message through `log` and/or `tracing`.
+pub fn forward(message: &LogMessage) {
+    let text = message.text().trim_end();
+
+    FORWARDING.with(|forwarding| forwarding.set(true));
+
+    #[cfg(feature = "log")]
+    if let Some(level) = to_log_level(message.level()) {
+        log::logger().log(
+            &log::Record::builder()
+                .args(format_args!("{}", text))
+                .level(level)
+                .target(message.prefix())
+                .build(),
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    {
+        let prefix = message.prefix();
+        match message.level() {
+            LogLevel::Fatal | LogLevel::Error => tracing::error!(target: "mpv", prefix, "{}", text),
+            LogLevel::Warn => tracing::warn!(target: "mpv", prefix, "{}", text),
+            LogLevel::Info => tracing::info!(target: "mpv", prefix, "{}", text),
+            LogLevel::V => tracing::debug!(target: "mpv", prefix, "{}", text),
+            LogLevel::Debug | LogLevel::Trace => tracing::trace!(target: "mpv", prefix, "{}", text),
+            LogLevel::None => {}
+        }
+    }
+
+    FORWARDING.with(|forwarding| forwarding.set(false));
+}
+
+#[cfg(feature = "log")]
+fn to_log_level(level: LogLevel) -> Option<log::Level> {
+    match level {
+        LogLevel::None => None,
+        LogLevel::Fatal | LogLevel::Error => Some(log::Level::Error),
+        LogLevel::Warn => Some(log::Level::Warn),
+        LogLevel::Info => Some(log::Level::Info),
+        LogLevel::V => Some(log::Level::Debug),
+        LogLevel::Debug | LogLevel::Trace => Some(log::Level::Trace),
+    }
+}
+
+fn max_level() -> LogLevel {
+    #[allow(unused_mut)]
+    let mut level = LogLevel::None;
+
+    #[cfg(feature = "log")]
+    {
+        level = level.max(match log::max_level() {
+            log::LevelFilter::Off => LogLevel::None,
+            log::LevelFilter::Error => LogLevel::Error,
+            log::LevelFilter::Warn => LogLevel::Warn,
+            log::LevelFilter::Info => LogLevel::Info,
+            log::LevelFilter::Debug => LogLevel::V,
+            log::LevelFilter::Trace => LogLevel::Debug,
+        });
+    }
+
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::level_filters::LevelFilter;
+
+        let current = LevelFilter::current();
+        level = level.max(if current == LevelFilter::OFF {
+            LogLevel::None
+        } else if current == LevelFilter::ERROR {
+            LogLevel::Error
+        } else if current == LevelFilter::WARN {
+            LogLevel::Warn
+        } else if current == LevelFilter::INFO {
+            LogLevel::Info
+        } else if current == LevelFilter::DEBUG {
+            LogLevel::V
+        } else {
+            LogLevel::Debug
+        });
+    }
+
+    level
+}
+
+/// A `log::Log` implementation writing into mpv's terminal and log file.
+///
+/// Records are printed with the `print-text` command as `[<client name>] <message>`,
+/// so they interleave with the output of mpv itself.
+#[cfg(feature = "log")]
+pub struct MpvLogger {
+    /// A client of its own, so the logger can be used from any thread. It is
+    /// destroyed with the handle the logger was created from, records are
+    /// then dropped.
+    client: Arc<Mutex<Option<Client>>>,
+    name: String,
+    level: log::LevelFilter,
+}
+
+#[cfg(feature = "log")]
+fn lock(client: &Mutex<Option<Client>>) -> MutexGuard<'_, Option<Client>> {
+    client.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(feature = "log")]
+impl MpvLogger {
+    pub fn new(handle: &mut Handle, level: log::LevelFilter) -> Result<Self> {
+        let name = handle.name().to_owned();
+        let mut client = handle.create_client(format!("{}-log", name))?;
+        // Nobody waits for the events of this client.
+        for kind in EventKind::ALL {
+            let _ = client.request_event(kind, false);
+        }
+
+        let client = Arc::new(Mutex::new(Some(client)));
+        let owned = client.clone();
+        dispatch::on_remove(unsafe { handle.as_ptr() }, Box::new(move || drop(lock(&owned).take())));
+        Ok(Self { client, name, level })
+    }
+
+    /// Install the logger as the global `log` logger.
+    pub fn init(self) -> std::result::Result<(), log::SetLoggerError> {
+        let level = self.level;
+        log::set_boxed_logger(Box::new(self)).map(|()| log::set_max_level(level))
+    }
+}
+
+#[cfg(feature = "log")]
+impl log::Log for MpvLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= self.level && !FORWARDING.with(|forwarding| forwarding.get())
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let text = match record.level() {
+            log::Level::Error | log::Level::Warn => format!("[{}] {}: {}", self.name, record.level(), record.args()),
+            _ => format!("[{}] {}", self.name, record.args()),
+        };
+        if let Some(client) = lock(&self.client).as_mut() {
+            let _ = client.command(["print-text", &text]);
+        }
+    }
+
+    fn flush(&self) {}
+}
@@ -0,0 +1,323 @@
+//! A selectable list on the OSD, to pick a track, a chapter or a playlist
+//! entry, like `mp.input.select` in Lua.
+//!
+//! While the menu is open, it takes over the navigation keys with forced key
+//! bindings: arrows, page up/down, home/end and the mouse wheel move the
+//! cursor, `ENTER` selects the item, `ESC` closes the menu, and typed text
+//! filters the items (`BS` deletes a character, `Ctrl+u` clears the filter).
+
+use super::dispatch;
+use super::input::{self, Binding, InputOverlay};
+use super::osd::{AssBuilder, OsdDimensions};
+use super::{Handle, Result};
+
+use std::sync::{Arc, Mutex};
+
+type OnClose = Box<dyn FnOnce(&mut Handle, Option<usize>) + Send>;
+
+/// The items of a menu, shown with `Handle::open_menu`.
+#[derive(Debug, Default, Clone)]
+pub struct Menu {
+    title: String,
+    items: Vec<MenuItem>,
+    selected: usize,
+    max_rows: Option<usize>,
+}
+
+/// An entry of a `Menu`.
+#[derive(Debug, Default, Clone)]
+pub struct MenuItem {
+    pub label: String,
+    /// Secondary text shown after the label, e.g. the language of a track.
+    pub hint: Option<String>,
+}
+
+pub(crate) struct MenuState {
+    menu: Menu,
+    filter: String,
+    /// Indices of the items matching the filter.
+    matches: Vec<usize>,
+    /// Position of the cursor in `matches`.
+    cursor: usize,
+    scroll: usize,
+    /// Rows shown by the last render.
+    rows: usize,
+    input: Option<InputOverlay>,
+    on_close: Option<OnClose>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Move(isize),
+    Page(isize),
+    First,
+    Last,
+    Submit,
+    Cancel,
+    Backspace,
+    ClearFilter,
+    Type,
+}
+
+const BINDINGS: &[Binding<Action>] = &[
+    ("UP", "menu-up", Action::Move(-1)),
+    ("DOWN", "menu-down", Action::Move(1)),
+    ("WHEEL_UP", "menu-wheel-up", Action::Move(-1)),
+    ("WHEEL_DOWN", "menu-wheel-down", Action::Move(1)),
+    ("PGUP", "menu-page-up", Action::Page(-1)),
+    ("PGDWN", "menu-page-down", Action::Page(1)),
+    ("HOME", "menu-home", Action::First),
+    ("END", "menu-end", Action::Last),
+    ("ENTER", "menu-submit", Action::Submit),
+    ("KP_ENTER", "menu-submit-kp", Action::Submit),
+    ("ESC", "menu-cancel", Action::Cancel),
+    ("BS", "menu-backspace", Action::Backspace),
+    ("Ctrl+u", "menu-clear", Action::ClearFilter),
+    ("ANY_UNICODE", "menu-type", Action::Type),
+];
+
+impl Menu {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_owned(),
+            ..Self::default()
+        }
+    }
+
+    /// Add an item.
+    pub fn item(mut self, label: &str) -> Self {
+        self.items.push(MenuItem {
+            label: label.to_owned(),
+            hint: None,
+        });
+        self
+    }
+
+    /// Add an item with a hint.
+    pub fn item_with_hint(mut self, label: &str, hint: &str) -> Self {
+        self.items.push(MenuItem {
+            label: label.to_owned(),
+            hint: Some(hint.to_owned()),
+        });
+        self
+    }
+
+    /// Add items.
+    pub fn items<I: IntoIterator<Item = MenuItem>>(mut self, items: I) -> Self {
+        self.items.extend(items);
+        self
+    }
+
+    /// Put the cursor on the item at `index` when the menu opens, e.g. on the
+    /// current track.
+    pub fn selected(mut self, index: usize) -> Self {
+        self.selected = index;
+        self
+    }
+
+    /// Limit the number of visible rows. By default, as many rows as fit on the
+    /// OSD are shown.
+    pub fn max_rows(mut self, rows: usize) -> Self {
+        self.max_rows = Some(rows);
+        self
+    }
+}
+
+impl MenuState {
+    fn refilter(&mut self) {
+        let current = self.matches.get(self.cursor).copied();
+        let filter = self.filter.to_lowercase();
+        self.matches = (0..self.menu.items.len())
+            .filter(|&i| {
+                let label = self.menu.items[i].label.to_lowercase();
+                filter.split_whitespace().all(|word| label.contains(word))
+            })
+            .collect();
+        self.cursor = current
+            .and_then(|current| self.matches.iter().position(|&i| i == current))
+            .unwrap_or(0);
+    }
+
+    fn apply(&mut self, action: Action, text: Option<&str>) {
+        let last = self.matches.len().saturating_sub(1);
+        match action {
+            // Single steps wrap around, like select.lua.
+            Action::Move(-1) if self.cursor == 0 => self.cursor = last,
+            Action::Move(1) if self.cursor == last => self.cursor = 0,
+            Action::Move(delta) => self.cursor = self.cursor.saturating_add_signed(delta).min(last),
+            Action::Page(pages) => {
+                self.cursor = self.cursor.saturating_add_signed(pages * self.rows as isize).min(last);
+            }
+            Action::First => self.cursor = 0,
+            Action::Last => self.cursor = last,
+            Action::Backspace => {
+                self.filter.pop();
+                self.refilter();
+            }
+            Action::ClearFilter => {
+                self.filter.clear();
+                self.refilter();
+            }
+            Action::Type => {
+                self.filter.push_str(text.unwrap_or_default());
+                self.refilter();
+            }
+            Action::Submit | Action::Cancel => {}
+        }
+    }
+
+    fn render(&mut self, dimensions: &OsdDimensions) -> String {
+        let font_size = input::font_size(dimensions);
+        let margin = font_size;
+        let line_height = font_size * 1.25;
+        let fitting = ((dimensions.h as f64 - 2. * margin) / line_height) as usize;
+        let rows = fitting
+            .saturating_sub(2)
+            .min(self.menu.max_rows.unwrap_or(usize::MAX))
+            .max(1);
+        self.rows = rows;
+
+        // Keep the cursor visible.
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        } else if self.cursor >= self.scroll + rows {
+            self.scroll = self.cursor + 1 - rows;
+        }
+        self.scroll = self.scroll.min(self.matches.len().saturating_sub(rows));
+
+        let mut ass = AssBuilder::new();
+        input::background(&mut ass, 0., 0., dimensions.w as f64, dimensions.h as f64);
+
+        ass.new_event()
+            .an(7)
+            .pos(margin, margin)
+            .font_size(font_size)
+            .border(font_size / 16.)
+            .bold(true)
+            .text(&self.menu.title);
+        if !self.matches.is_empty() {
+            ass.text(&format!(" ({}/{})", self.cursor + 1, self.matches.len()));
+        }
+        ass.bold(false);
+        ass.append("\\N").color(0x66ccff).text(&format!("/{}", self.filter));
+
+        if self.matches.is_empty() {
+            ass.append("\\N").color(0x999999).text("No matching item");
+        }
+        for (row, &index) in self.matches.iter().enumerate().skip(self.scroll).take(rows) {
+            let item = &self.menu.items[index];
+            ass.append("\\N");
+            if row == self.cursor {
+                ass.color(0xffd700).text("➤ ");
+            } else {
+                ass.color(0xffffff).text("   ");
+            }
+            ass.text(&item.label);
+            if let Some(hint) = &item.hint {
+                ass.color(0x999999).text(&format!("  {}", hint));
+            }
+        }
+        ass.into()
+    }
+}
+
+impl Handle {
+    /// Show `menu` on the OSD, and call `on_close` with the index of the
+    /// selected item when the user picks one, or with `None` when the menu is
+    /// closed without a selection. Opening a menu closes the previous one.
+    ///
+    /// The keys are handled by `Handle::wait_event`.
+    pub fn open_menu<F>(&mut self, menu: Menu, on_close: F) -> Result<()>
+    where
+        F: FnOnce(&mut Handle, Option<usize>) + Send + 'static,
+    {
+        self.close_menu_with(None)?;
+
+        let handle = unsafe { self.as_ptr() };
+        let mut state = MenuState {
+            matches: (0..menu.items.len()).collect(),
+            cursor: 0,
+            menu,
+            filter: String::new(),
+            scroll: 0,
+            rows: 1,
+            input: None,
+            on_close: Some(Box::new(on_close)),
+        };
+        state.cursor = state.menu.selected.min(state.matches.len().saturating_sub(1));
+        let state = Arc::new(Mutex::new(state));
+        dispatch::set_menu(handle, Some(state.clone()));
+
+        let render = state.clone();
+        let input = InputOverlay::open(
+            self,
+            BINDINGS,
+            move |dimensions| render.lock().unwrap_or_else(|e| e.into_inner()).render(dimensions),
+            Handle::menu_action,
+        );
+        match input {
+            Ok(input) => {
+                state.lock().unwrap_or_else(|e| e.into_inner()).input = Some(input);
+                Ok(())
+            }
+            Err(e) => {
+                drop(dispatch::set_menu(handle, None));
+                Err(e)
+            }
+        }
+    }
+
+    /// Close the open menu, if any. Its `on_close` callback is called with
+    /// `None`.
+    pub fn close_menu(&mut self) -> Result<()> {
+        self.close_menu_with(None)
+    }
+
+    fn menu_action(&mut self, action: Action, text: Option<String>) {
+        let Some(state) = dispatch::menu(unsafe { self.as_ptr() }) else {
+            return;
+        };
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let result = match action {
+            Action::Submit => match state.matches.get(state.cursor).copied() {
+                Some(index) => {
+                    drop(state);
+                    self.close_menu_with(Some(index))
+                }
+                None => return,
+            },
+            Action::Cancel => {
+                drop(state);
+                self.close_menu_with(None)
+            }
+            action => {
+                state.apply(action, text.as_deref());
+                let overlay = state.input.as_ref().map(InputOverlay::id);
+                drop(state);
+                match overlay {
+                    Some(id) => self.render_overlay(id),
+                    None => Ok(()),
+                }
+            }
+        };
+        if let Err(e) = result {
+            self.print_warning(format!("menu: {}", e));
+        }
+    }
+
+    fn close_menu_with(&mut self, selection: Option<usize>) -> Result<()> {
+        let Some(state) = dispatch::set_menu(unsafe { self.as_ptr() }, None) else {
+            return Ok(());
+        };
+        let (input, on_close) = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            (state.input.take(), state.on_close.take())
+        };
+
+        let result = input.map_or(Ok(()), |input| input.close(self));
+        if let Some(on_close) = on_close {
+            on_close(self, selection);
+        }
+        result
+    }
+}
@@ -0,0 +1,147 @@
+use super::dispatch;
+use super::{ClientMessage, Handle};
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+pub(crate) type Handler = Arc<Mutex<dyn FnMut(&mut Handle, &[&str]) -> Result<(), ArgsError> + Send>>;
+
+/// Error returned when the arguments of a script message can't be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArgsError(String);
+
+/// Conversion of the arguments of a script message into a typed value.
+///
+/// It is implemented for tuples of `FromStr` types, which require exactly as
+/// many arguments as the tuple has fields, and for `Vec` of `FromStr` types,
+/// which accept any number of arguments.
+pub trait FromArgs: Sized {
+    fn from_args(args: &[&str]) -> Result<Self, ArgsError>;
+}
+
+impl ArgsError {
+    pub fn new(message: impl Into<String>) -> Self {
+        Self(message.into())
+    }
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl error::Error for ArgsError {}
+
+fn parse_arg<T>(args: &[&str], index: usize) -> Result<T, ArgsError>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    let arg = args[index];
+    arg.parse()
+        .map_err(|e| ArgsError(format!("invalid argument {} '{}': {}", index + 1, arg, e)))
+}
+
+fn check_count(args: &[&str], expected: usize) -> Result<(), ArgsError> {
+    match args.len() {
+        n if n == expected => Ok(()),
+        n => Err(ArgsError(format!(
+            "expected {} argument{}, got {}",
+            expected,
+            if expected == 1 { "" } else { "s" },
+            n
+        ))),
+    }
+}
+
+impl FromArgs for () {
+    fn from_args(args: &[&str]) -> Result<Self, ArgsError> {
+        check_count(args, 0)
+    }
+}
+
+impl<T> FromArgs for Vec<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    fn from_args(args: &[&str]) -> Result<Self, ArgsError> {
+        (0..args.len()).map(|i| parse_arg(args, i)).collect()
+    }
+}
+
+macro_rules! tuple_from_args {
+    ($count:expr; $($name:ident $index:tt),+) => {
+        impl<$($name),+> FromArgs for ($($name,)+)
+        where
+            $($name: FromStr, $name::Err: fmt::Display,)+
+        {
+            fn from_args(args: &[&str]) -> Result<Self, ArgsError> {
+                check_count(args, $count)?;
+                Ok(($(parse_arg::<$name>(args, $index)?,)+))
+            }
+        }
+    };
+}
+
+tuple_from_args!(1; A 0);
+tuple_from_args!(2; A 0, B 1);
+tuple_from_args!(3; A 0, B 1, C 2);
+tuple_from_args!(4; A 0, B 1, C 2, D 3);
+tuple_from_args!(5; A 0, B 1, C 2, D 3, E 4);
+tuple_from_args!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+tuple_from_args!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+tuple_from_args!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+impl Handle {
+    /// Call `handler` when the script message `name` is received, like
+    /// `mp.register_script_message` in Lua. Registering the same name again
+    /// replaces the handler.
+    ///
+    /// The arguments following the message name are parsed into `A` (see
+    /// `FromArgs`). Malformed messages are reported with a warning on the
+    /// terminal, and the handler isn't called.
+    ///
+    /// The handler is run by `Handle::wait_event`, which consumes the messages
+    /// of registered names.
+    pub fn register_script_message<A, F>(&mut self, name: &str, mut handler: F)
+    where
+        A: FromArgs,
+        F: FnMut(&mut Handle, A) + Send + 'static,
+    {
+        let handler: Handler = Arc::new(Mutex::new(move |handle: &mut Handle, args: &[&str]| {
+            A::from_args(args).map(|args| handler(handle, args))
+        }));
+        dispatch::add_message_handler(unsafe { self.as_ptr() }, name.to_owned(), handler);
+    }
+
+    /// Remove a handler registered with `Handle::register_script_message`.
+    pub fn unregister_script_message(&mut self, name: &str) {
+        drop(dispatch::remove_message_handler(unsafe { self.as_ptr() }, name));
+    }
+
+    /// Run the handler registered for a client message. Return whether the
+    /// message was handled.
+    pub(crate) fn dispatch_script_message(&mut self, message: &ClientMessage) -> bool {
+        let Some(&name) = message.args().first() else {
+            return false;
+        };
+        let Some(handler) = dispatch::message_handler(unsafe { self.as_ptr() }, name) else {
+            return false;
+        };
+        let Ok(args) = message.try_args() else {
+            self.print_warning(format!("ignoring script message '{}': invalid UTF-8 argument", name));
+            return true;
+        };
+        let args = &args[1..];
+
+        let result = handler.lock().unwrap_or_else(|e| e.into_inner())(self, args);
+        if let Err(e) = result {
+            self.print_warning(format!("ignoring script message '{}': {}", name, e));
+        }
+        true
+    }
+}
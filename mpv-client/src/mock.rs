@@ -0,0 +1,348 @@
+//! A fake player to unit test plugin logic without libmpv.
+//!
+//! `MockPlayer` implements `Player` with scripted property values, records the
+//! commands it receives, and returns the events injected by the test:
+//!
+//! ```ignore
+//! use mpv_client::mock::MockPlayer;
+//! use mpv_client::{Event, Node};
+//!
+//! let mut player = MockPlayer::new().with_property("pause", Node::Bool(false));
+//! player.push_event(Event::FileLoaded);
+//! my_plugin::run_once(&mut player);
+//! assert!(player.took_command(&["show-text", "Paused"]));
+//! assert_eq!(player.property("pause"), Some(&Node::Bool(true)));
+//! ```
+//!
+//! `Player::set_property` changes the property, it isn't recorded as a
+//! command.
+
+use super::format::to_node;
+use super::owned::{OwnedProperty, Value};
+use super::{mpv_error_MPV_ERROR_PROPERTY_FORMAT, mpv_error_MPV_ERROR_PROPERTY_NOT_FOUND, mpv_format};
+use super::{ClientMessage, Error, Event, Format, Hook, Node, Player, Property, Result};
+
+use std::collections::{HashMap, VecDeque};
+
+/// A fake player, see the module documentation.
+#[derive(Default)]
+pub struct MockPlayer {
+    properties: HashMap<String, Node>,
+    command_results: HashMap<String, Result<Node>>,
+    commands: Vec<Node>,
+    observed: Vec<(u64, String, mpv_format)>,
+    hooks: Vec<(u64, String, i32)>,
+    next_hook_id: u64,
+    continued_hooks: Vec<u64>,
+    events: VecDeque<Event>,
+}
+
+/// The name of a command given as array or map.
+fn command_name(args: &Node) -> Option<&str> {
+    match args {
+        Node::Array(args) => match args.first() {
+            Some(Node::String(name)) => Some(name),
+            _ => None,
+        },
+        Node::Map(args) => match args.get("name") {
+            Some(Node::String(name)) => Some(name),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+impl MockPlayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the initial value of a property.
+    pub fn with_property(mut self, name: &str, value: Node) -> Self {
+        self.properties.insert(name.to_owned(), value);
+        self
+    }
+
+    /// Change a property, as if the player changed it. The observers of the
+    /// property receive a `PropertyChange` event.
+    pub fn set(&mut self, name: &str, value: Node) {
+        self.properties.insert(name.to_owned(), value);
+        self.notify(name);
+    }
+
+    /// Remove a property, reading it then fails with
+    /// `MPV_ERROR_PROPERTY_NOT_FOUND`. The observers receive a `PropertyChange`
+    /// event without data.
+    pub fn unset(&mut self, name: &str) {
+        self.properties.remove(name);
+        self.notify(name);
+    }
+
+    /// The current value of a property.
+    pub fn property(&self, name: &str) -> Option<&Node> {
+        self.properties.get(name)
+    }
+
+    /// Return `result` when the command `name` is run, instead of
+    /// `Ok(Node::None)`.
+    pub fn set_command_result(&mut self, name: &str, result: Result<Node>) {
+        self.command_results.insert(name.to_owned(), result);
+    }
+
+    /// The commands run so far, as arrays of strings for `Player::command` and
+    /// `Player::command_ret`, or as given to `Player::command_node`.
+    pub fn commands(&self) -> &[Node] {
+        &self.commands
+    }
+
+    /// Whether the command `args` was run with `Player::command` or
+    /// `Player::command_ret`.
+    pub fn took_command(&self, args: &[&str]) -> bool {
+        let args = Node::Array(args.iter().map(|arg| Node::String(arg.to_string())).collect());
+        self.commands.contains(&args)
+    }
+
+    /// Forget the commands run so far.
+    pub fn clear_commands(&mut self) {
+        self.commands.clear();
+    }
+
+    /// Queue an event, returned by `Player::wait_event`.
+    pub fn push_event(&mut self, event: Event) {
+        self.events.push_back(event);
+    }
+
+    /// Queue a client message, e.g. a `script-message`.
+    pub fn push_client_message(&mut self, args: &[&str]) {
+        self.push_event(Event::ClientMessage(ClientMessage::new(args)));
+    }
+
+    /// Queue a hook event for the first handler of `name` registered with
+    /// `Player::hook_add`, and return the hook id, or `None` if no handler was
+    /// registered.
+    pub fn push_hook(&mut self, name: &str) -> Option<u64> {
+        let reply = self
+            .hooks
+            .iter()
+            .filter(|(_, hook, _)| hook == name)
+            .min_by_key(|(_, _, priority)| *priority)
+            .map(|(reply, _, _)| *reply)?;
+        self.next_hook_id += 1;
+        let id = self.next_hook_id;
+        self.push_event(Event::Hook(reply, Hook::new(name, id)));
+        Some(id)
+    }
+
+    /// The ids passed to `Player::hook_continue`.
+    pub fn continued_hooks(&self) -> &[u64] {
+        &self.continued_hooks
+    }
+
+    /// Whether the property `name` is observed.
+    pub fn is_observed(&self, name: &str) -> bool {
+        self.observed.iter().any(|(_, observed, _)| observed == name)
+    }
+
+    fn property_change(&self, reply: u64, name: &str, format: mpv_format) -> Event {
+        let value = self
+            .properties
+            .get(name)
+            .and_then(|value| Value::convert(value, format))
+            .unwrap_or(Value::None);
+        Event::PropertyChange(reply, Property::from_owned(OwnedProperty::new(name, value)))
+    }
+
+    fn notify(&mut self, name: &str) {
+        let events: Vec<Event> = self
+            .observed
+            .iter()
+            .filter(|(_, observed, _)| observed == name)
+            .map(|(reply, name, format)| self.property_change(*reply, name, *format))
+            .collect();
+        self.events.extend(events);
+    }
+
+    fn run(&mut self, args: Node) -> Result<Node> {
+        let result = match command_name(&args).and_then(|name| self.command_results.get(name)) {
+            Some(Ok(node)) => Ok(node.clone()),
+            Some(Err(e)) => Err(*e),
+            None => Ok(Node::None),
+        };
+        self.commands.push(args);
+        result
+    }
+}
+
+impl Player for MockPlayer {
+    fn command<I, S>(&mut self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.command_ret(args).map(|_| ())
+    }
+
+    fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args = args.into_iter().map(|arg| Node::String(arg.as_ref().to_owned()));
+        self.run(Node::Array(args.collect()))
+    }
+
+    fn command_node(&mut self, args: Node) -> Result<Node> {
+        self.run(args)
+    }
+
+    fn get_property<T: Format>(&mut self, name: &str) -> Result<T> {
+        let value = self
+            .properties
+            .get(name)
+            .ok_or(Error::new(mpv_error_MPV_ERROR_PROPERTY_NOT_FOUND))?;
+        let value = Value::convert(value, T::MPV_FORMAT).ok_or(Error::new(mpv_error_MPV_ERROR_PROPERTY_FORMAT))?;
+        Property::from_owned(OwnedProperty::new(name, value))
+            .data::<T>()
+            .ok_or(Error::new(mpv_error_MPV_ERROR_PROPERTY_FORMAT))
+    }
+
+    fn set_property<T: Format>(&mut self, name: &str, data: T) -> Result<()> {
+        let value = to_node(data)?;
+        self.set(name, value);
+        Ok(())
+    }
+
+    /// Like mpv, the current value is sent right away as a `PropertyChange`.
+    fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> Result<()> {
+        self.observed.push((reply, name.to_owned(), T::MPV_FORMAT));
+        let event = self.property_change(reply, name, T::MPV_FORMAT);
+        self.push_event(event);
+        Ok(())
+    }
+
+    fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32> {
+        let count = self.observed.len();
+        self.observed.retain(|(reply, _, _)| *reply != registered_reply);
+        Ok((count - self.observed.len()) as i32)
+    }
+
+    fn hook_add(&mut self, reply: u64, name: &str, priority: i32) -> Result<()> {
+        self.hooks.push((reply, name.to_owned(), priority));
+        Ok(())
+    }
+
+    fn hook_continue(&mut self, id: u64) -> Result<()> {
+        self.continued_hooks.push(id);
+        Ok(())
+    }
+
+    /// Return the next queued event, or `Event::None` right away if there is
+    /// none: the timeout is ignored, so tests don't wait.
+    fn wait_event(&mut self, _timeout: f64) -> Event {
+        self.events.pop_front().unwrap_or(Event::None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn returns_scripted_events() {
+        let mut player = MockPlayer::new();
+        player.push_event(Event::FileLoaded);
+        player.push_client_message(&["my-script", "toggle"]);
+
+        assert!(matches!(player.wait_event(-1.), Event::FileLoaded));
+        match player.wait_event(-1.) {
+            Event::ClientMessage(message) => assert_eq!(message.args(), ["my-script", "toggle"]),
+            event => panic!("unexpected {}", event),
+        }
+        assert!(matches!(player.wait_event(-1.), Event::None));
+    }
+
+    #[test]
+    fn records_commands() {
+        let mut player = MockPlayer::new();
+        player.set_command_result("expand-text", Ok(Node::String(String::from("1"))));
+        player.set_command_result("stop", Err(Error::new(mpv_error_MPV_ERROR_PROPERTY_NOT_FOUND)));
+
+        player.command(["cycle", "pause"]).unwrap();
+        assert_eq!(
+            player.command_ret(["expand-text", "${volume}"]).unwrap(),
+            Node::String(String::from("1"))
+        );
+        assert!(player.command(["stop"]).is_err());
+        let node = Node::Map(HashMap::from([(
+            String::from("name"),
+            Node::String(String::from("quit")),
+        )]));
+        player.command_node(node.clone()).unwrap();
+
+        assert!(player.took_command(&["cycle", "pause"]));
+        assert!(player.took_command(&["stop"]));
+        assert!(!player.took_command(&["cycle"]));
+        assert_eq!(player.commands().len(), 4);
+        assert_eq!(player.commands()[3], node);
+
+        player.clear_commands();
+        assert!(player.commands().is_empty());
+    }
+
+    #[test]
+    fn scripts_properties() {
+        let mut player = MockPlayer::new().with_property("volume", Node::Int(50));
+        assert_eq!(player.get_property::<i64>("volume").unwrap(), 50);
+        assert_eq!(player.get_property::<f64>("volume").unwrap(), 50.);
+        assert_eq!(player.get_property::<String>("volume").unwrap(), "50");
+
+        player.set_property("pause", true).unwrap();
+        assert_eq!(player.property("pause"), Some(&Node::Bool(true)));
+        assert!(player.commands().is_empty());
+
+        player.unset("volume");
+        assert_eq!(
+            player.get_property::<i64>("volume").map_err(|e| e.code()),
+            Err(mpv_error_MPV_ERROR_PROPERTY_NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn notifies_observers() {
+        let mut player = MockPlayer::new().with_property("pause", Node::Bool(false));
+        player.observe_property::<bool>(1, "pause").unwrap();
+        assert!(player.is_observed("pause"));
+        match player.wait_event(0.) {
+            Event::PropertyChange(1, property) => assert_eq!(property.data::<bool>(), Some(false)),
+            event => panic!("unexpected {}", event),
+        }
+
+        player.set("pause", Node::Bool(true));
+        match player.wait_event(0.) {
+            Event::PropertyChange(1, property) => assert_eq!(property.data::<bool>(), Some(true)),
+            event => panic!("unexpected {}", event),
+        }
+
+        assert_eq!(player.unobserve_property(1).unwrap(), 1);
+        player.set("pause", Node::Bool(false));
+        assert!(matches!(player.wait_event(0.), Event::None));
+    }
+
+    #[test]
+    fn runs_hooks() {
+        let mut player = MockPlayer::new();
+        assert_eq!(player.push_hook("on_load"), None);
+
+        player.hook_add(7, "on_load", 0).unwrap();
+        let id = player.push_hook("on_load").unwrap();
+        match player.wait_event(0.) {
+            Event::Hook(7, hook) => {
+                assert_eq!(hook.name(), "on_load");
+                assert_eq!(hook.id(), id);
+            }
+            event => panic!("unexpected {}", event),
+        }
+        player.hook_continue(id).unwrap();
+        assert_eq!(player.continued_hooks(), [id]);
+    }
+}
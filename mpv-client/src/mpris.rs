@@ -0,0 +1,676 @@
+//! MPRIS2 D-Bus interface, for the media controls of desktop environments.
+//!
+//! `Mpris` publishes `org.mpris.MediaPlayer2` and
+//! `org.mpris.MediaPlayer2.Player` on the session bus. The properties follow
+//! the observed `pause`, `volume`, `metadata`, `playlist-pos`... and the
+//! method calls are translated to commands:
+//!
+//! ```ignore
+//! use mpv_client::mpris::Mpris;
+//!
+//! let service = Mpris::new().start(&mut handle)?;
+//! ```
+//!
+//! `Mpris::address` connects to another bus, e.g. a private `dbus-daemon` in
+//! tests.
+
+use super::{Client, Event, Handle, Node};
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use zbus::blocking::connection::Builder;
+use zbus::blocking::Connection;
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::{fdo, interface, object_server::SignalContext};
+
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const ROOT_INTERFACE: &str = "org.mpris.MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+/// The observed properties, observed with their index as reply.
+const PROPERTIES: [&str; 15] = [
+    "pause",
+    "idle-active",
+    "duration",
+    "volume",
+    "speed",
+    "loop-file",
+    "loop-playlist",
+    "shuffle",
+    "fullscreen",
+    "seekable",
+    "media-title",
+    "path",
+    "metadata",
+    "playlist-pos",
+    "playlist-count",
+];
+
+/// Longest wait of the observer thread before checking if it was stopped.
+const POLL_INTERVAL: f64 = 0.1;
+
+/// The MPRIS service configuration, see the module documentation.
+pub struct Mpris {
+    name: String,
+    identity: String,
+    address: Option<String>,
+}
+
+/// A running MPRIS service. The bus name is released when dropped.
+pub struct MprisService {
+    connection: Connection,
+    stopped: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The last values of the observed properties.
+#[derive(Default)]
+struct State {
+    properties: HashMap<String, Node>,
+}
+
+/// The interfaces share the state, and the client running the commands. The
+/// client is dropped when the player shuts down, so that it doesn't keep the
+/// player alive.
+#[derive(Clone)]
+struct Shared {
+    state: Arc<Mutex<State>>,
+    control: Arc<Mutex<Option<Client>>>,
+}
+
+struct RootInterface {
+    shared: Shared,
+    identity: String,
+}
+
+struct PlayerInterface {
+    shared: Shared,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn failed(e: super::Error) -> fdo::Error {
+    fdo::Error::Failed(e.to_string())
+}
+
+/// Microseconds, the time unit of MPRIS.
+fn micros(seconds: f64) -> i64 {
+    (seconds * 1e6) as i64
+}
+
+fn owned(value: Value) -> Option<OwnedValue> {
+    OwnedValue::try_from(value).ok()
+}
+
+impl State {
+    fn node(&self, name: &str) -> Option<&Node> {
+        self.properties.get(name)
+    }
+
+    fn bool(&self, name: &str) -> bool {
+        matches!(self.node(name), Some(Node::Bool(true)))
+    }
+
+    fn f64(&self, name: &str) -> Option<f64> {
+        match self.node(name)? {
+            Node::Double(value) => Some(*value),
+            Node::Int(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+
+    fn i64(&self, name: &str) -> Option<i64> {
+        match self.node(name)? {
+            Node::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn str(&self, name: &str) -> Option<&str> {
+        match self.node(name)? {
+            Node::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// A loop property is either `no`/`false`, or a count/`inf`.
+    fn loops(&self, name: &str) -> bool {
+        !matches!(self.node(name), None | Some(Node::Bool(false)) | Some(Node::Int(0))) && self.str(name) != Some("no")
+    }
+
+    /// The entry of the `metadata` property named `key`, ignoring case.
+    fn tag(&self, key: &str) -> Option<&str> {
+        match self.node("metadata")? {
+            Node::Map(tags) => tags.iter().find_map(|(name, value)| match value {
+                Node::String(value) if name.eq_ignore_ascii_case(key) => Some(value.as_str()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    }
+
+    fn track_id(&self) -> String {
+        match self.i64("playlist-pos") {
+            Some(pos) if pos >= 0 && !self.bool("idle-active") => format!("/io/mpv/Track/{}", pos),
+            _ => String::from(NO_TRACK),
+        }
+    }
+
+    fn playback_status(&self) -> &'static str {
+        if self.bool("idle-active") {
+            "Stopped"
+        } else if self.bool("pause") {
+            "Paused"
+        } else {
+            "Playing"
+        }
+    }
+
+    fn loop_status(&self) -> &'static str {
+        if self.loops("loop-file") {
+            "Track"
+        } else if self.loops("loop-playlist") {
+            "Playlist"
+        } else {
+            "None"
+        }
+    }
+
+    fn can_go_next(&self) -> bool {
+        let (pos, count) = (self.i64("playlist-pos"), self.i64("playlist-count"));
+        matches!((pos, count), (Some(pos), Some(count)) if pos + 1 < count) || self.loops("loop-playlist")
+    }
+
+    fn can_go_previous(&self) -> bool {
+        matches!(self.i64("playlist-pos"), Some(pos) if pos > 0) || self.loops("loop-playlist")
+    }
+
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        let mut metadata = HashMap::new();
+        let mut insert = |key: &str, value: Option<OwnedValue>| {
+            if let Some(value) = value {
+                metadata.insert(key.to_owned(), value);
+            }
+        };
+
+        let track_id = self.track_id();
+        insert(
+            "mpris:trackid",
+            ObjectPath::try_from(track_id.as_str())
+                .ok()
+                .and_then(|path| owned(path.into())),
+        );
+        if track_id == NO_TRACK {
+            return metadata;
+        }
+
+        insert(
+            "mpris:length",
+            self.f64("duration").and_then(|duration| owned(micros(duration).into())),
+        );
+        insert(
+            "xesam:title",
+            self.str("media-title").and_then(|title| owned(title.into())),
+        );
+        if let Some(path) = self.str("path") {
+            let url = if path.contains("://") {
+                path.to_owned()
+            } else {
+                format!("file://{}", path)
+            };
+            insert("xesam:url", owned(url.into()));
+        }
+        insert("xesam:album", self.tag("album").and_then(|album| owned(album.into())));
+        for (tag, key) in [
+            ("artist", "xesam:artist"),
+            ("album_artist", "xesam:albumArtist"),
+            ("genre", "xesam:genre"),
+            ("comment", "xesam:comment"),
+        ] {
+            insert(key, self.tag(tag).and_then(|value| owned(vec![value].into())));
+        }
+        for (tag, key) in [("track", "xesam:trackNumber"), ("disc", "xesam:discNumber")] {
+            // e.g. "3/12"
+            let number = self
+                .tag(tag)
+                .and_then(|value| value.split('/').next())
+                .and_then(|number| number.trim().parse::<i32>().ok());
+            insert(key, number.and_then(|number| owned(number.into())));
+        }
+        metadata
+    }
+}
+
+impl Shared {
+    fn state(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    /// Run `f` with the client, unless the player shut down.
+    fn control<R>(&self, f: impl FnOnce(&mut Client) -> super::Result<R>) -> fdo::Result<R> {
+        match lock(&self.control).as_mut() {
+            Some(client) => f(client).map_err(failed),
+            None => Err(fdo::Error::Failed(String::from("the player shut down"))),
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> fdo::Result<()> {
+        self.control(|client| client.command(args))
+    }
+
+    fn set<T: super::Format>(&self, name: &str, value: T) -> fdo::Result<()> {
+        self.control(|client| client.set_property(name, value))
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2")]
+impl RootInterface {
+    fn raise(&self) {}
+
+    fn quit(&self) -> fdo::Result<()> {
+        self.shared.command(&["quit"])
+    }
+
+    #[zbus(property)]
+    fn can_quit(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_raise(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn can_set_fullscreen(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn fullscreen(&self) -> bool {
+        self.shared.state().bool("fullscreen")
+    }
+
+    #[zbus(property)]
+    fn set_fullscreen(&mut self, fullscreen: bool) -> fdo::Result<()> {
+        self.shared.set("fullscreen", fullscreen)
+    }
+
+    #[zbus(property)]
+    fn has_track_list(&self) -> bool {
+        false
+    }
+
+    #[zbus(property)]
+    fn identity(&self) -> String {
+        self.identity.clone()
+    }
+
+    #[zbus(property)]
+    fn desktop_entry(&self) -> String {
+        String::from("mpv")
+    }
+
+    #[zbus(property)]
+    fn supported_uri_schemes(&self) -> Vec<String> {
+        [
+            "file", "http", "https", "ftp", "rtmp", "rtsp", "smb", "dvd", "bd", "cdda",
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+
+    #[zbus(property)]
+    fn supported_mime_types(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+#[interface(name = "org.mpris.MediaPlayer2.Player")]
+impl PlayerInterface {
+    fn next(&self) -> fdo::Result<()> {
+        self.shared.command(&["playlist-next"])
+    }
+
+    fn previous(&self) -> fdo::Result<()> {
+        self.shared.command(&["playlist-prev"])
+    }
+
+    fn pause(&self) -> fdo::Result<()> {
+        self.shared.command(&["set", "pause", "yes"])
+    }
+
+    fn play_pause(&self) -> fdo::Result<()> {
+        self.shared.command(&["cycle", "pause"])
+    }
+
+    fn stop(&self) -> fdo::Result<()> {
+        self.shared.command(&["stop"])
+    }
+
+    fn play(&self) -> fdo::Result<()> {
+        self.shared.command(&["set", "pause", "no"])
+    }
+
+    /// `offset` is in microseconds.
+    fn seek(&self, offset: i64) -> fdo::Result<()> {
+        let offset = (offset as f64 / 1e6).to_string();
+        self.shared.command(&["seek", &offset, "relative"])
+    }
+
+    /// Ignored unless `track_id` is the current track, as required by MPRIS.
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) -> fdo::Result<()> {
+        if track_id.as_str() != self.shared.state().track_id() {
+            return Ok(());
+        }
+        let position = (position as f64 / 1e6).to_string();
+        self.shared.command(&["seek", &position, "absolute"])
+    }
+
+    fn open_uri(&self, uri: String) -> fdo::Result<()> {
+        self.shared.command(&["loadfile", &uri])
+    }
+
+    #[zbus(signal)]
+    async fn seeked(ctxt: &SignalContext<'_>, position: i64) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+        self.shared.state().playback_status().to_owned()
+    }
+
+    #[zbus(property)]
+    fn loop_status(&self) -> String {
+        self.shared.state().loop_status().to_owned()
+    }
+
+    #[zbus(property)]
+    fn set_loop_status(&mut self, status: String) -> fdo::Result<()> {
+        let (file, playlist) = match status.as_str() {
+            "Track" => ("inf", "no"),
+            "Playlist" => ("no", "inf"),
+            _ => ("no", "no"),
+        };
+        self.shared.set("loop-file", file.to_owned())?;
+        self.shared.set("loop-playlist", playlist.to_owned())
+    }
+
+    #[zbus(property)]
+    fn rate(&self) -> f64 {
+        self.shared.state().f64("speed").unwrap_or(1.)
+    }
+
+    #[zbus(property)]
+    fn set_rate(&mut self, rate: f64) -> fdo::Result<()> {
+        // A rate of 0 means pausing, see the specification.
+        if rate == 0. {
+            return self.shared.set("pause", true);
+        }
+        self.shared.set("speed", rate)
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+        self.shared.state().bool("shuffle")
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, shuffle: bool) -> fdo::Result<()> {
+        let command = if shuffle {
+            "playlist-shuffle"
+        } else {
+            "playlist-unshuffle"
+        };
+        self.shared.command(&[command])?;
+        self.shared.set("shuffle", shuffle)
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+        self.shared.state().metadata()
+    }
+
+    /// Between 0 and 1, mpv's volume goes from 0 to 100 and above.
+    #[zbus(property)]
+    fn volume(&self) -> f64 {
+        self.shared.state().f64("volume").unwrap_or(100.) / 100.
+    }
+
+    #[zbus(property)]
+    fn set_volume(&mut self, volume: f64) -> fdo::Result<()> {
+        self.shared.set("volume", volume.max(0.) * 100.)
+    }
+
+    /// Read on demand, MPRIS clients don't expect changes to be signaled.
+    #[zbus(property(emits_changed_signal = "false"))]
+    fn position(&self) -> i64 {
+        self.shared
+            .control(|client| client.get_property::<f64>("time-pos"))
+            .map_or(0, micros)
+    }
+
+    #[zbus(property)]
+    fn minimum_rate(&self) -> f64 {
+        0.01
+    }
+
+    #[zbus(property)]
+    fn maximum_rate(&self) -> f64 {
+        100.
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+        self.shared.state().can_go_next()
+    }
+
+    #[zbus(property)]
+    fn can_go_previous(&self) -> bool {
+        self.shared.state().can_go_previous()
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+        self.shared.state().bool("seekable")
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+        true
+    }
+}
+
+impl Default for Mpris {
+    fn default() -> Self {
+        Self {
+            name: format!("mpv.instance{}", std::process::id()),
+            identity: String::from("mpv Media Player"),
+            address: None,
+        }
+    }
+}
+
+impl Mpris {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Own the bus name `org.mpris.MediaPlayer2.<name>`, by default
+    /// `org.mpris.MediaPlayer2.mpv.instance<pid>`.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_owned();
+        self
+    }
+
+    /// The name of the player shown to the user.
+    pub fn identity(mut self, identity: &str) -> Self {
+        self.identity = identity.to_owned();
+        self
+    }
+
+    /// Connect to the bus at `address`, e.g. `unix:path=/tmp/bus`, instead of
+    /// the session bus.
+    pub fn address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_owned());
+        self
+    }
+
+    /// Publish the interfaces. The properties are observed by a client created
+    /// from `handle`, in a background thread, and the methods run commands
+    /// with another client.
+    pub fn start(self, handle: &mut Handle) -> zbus::Result<MprisService> {
+        let mpv_error = |e: super::Error| zbus::Error::Failure(e.to_string());
+        let mut observer = handle.create_client("mpris").map_err(mpv_error)?;
+        let control = handle.create_client("mpris-control").map_err(mpv_error)?;
+        for (reply, name) in PROPERTIES.iter().enumerate() {
+            observer
+                .observe_property::<Node>(reply as u64, name)
+                .map_err(mpv_error)?;
+        }
+
+        let shared = Shared {
+            state: Arc::new(Mutex::new(State::default())),
+            control: Arc::new(Mutex::new(Some(control))),
+        };
+        let builder = match &self.address {
+            Some(address) => Builder::address(address.as_str())?,
+            None => Builder::session()?,
+        };
+        let connection = builder
+            .serve_at(
+                OBJECT_PATH,
+                RootInterface {
+                    shared: shared.clone(),
+                    identity: self.identity,
+                },
+            )?
+            .serve_at(OBJECT_PATH, PlayerInterface { shared: shared.clone() })?
+            .name(format!("{}.{}", ROOT_INTERFACE, self.name))?
+            .build()?;
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let connection = connection.clone();
+            let stopped = stopped.clone();
+            thread::spawn(move || observe(observer, &shared, &connection, &stopped))
+        };
+
+        Ok(MprisService {
+            connection,
+            stopped,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl MprisService {
+    /// The D-Bus connection, e.g. to get its unique name.
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+}
+
+impl Drop for MprisService {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Emit `PropertiesChanged` for the given properties of `interface`.
+fn properties_changed(connection: &Connection, interface: &str, changed: HashMap<&str, Value>) {
+    if changed.is_empty() {
+        return;
+    }
+    let _ = connection.emit_signal(
+        None::<()>,
+        OBJECT_PATH,
+        "org.freedesktop.DBus.Properties",
+        "PropertiesChanged",
+        &(interface, changed, Vec::<&str>::new()),
+    );
+}
+
+/// Update the state from the property changes, and signal them, until the
+/// service is stopped or the player shuts down, then drop the client running
+/// the commands.
+fn observe(mut observer: Client, shared: &Shared, connection: &Connection, stopped: &AtomicBool) {
+    while !stopped.load(Ordering::SeqCst) {
+        match observer.wait_event(POLL_INTERVAL) {
+            Event::PropertyChange(_, property) => {
+                let name = property.name();
+                let mut state = shared.state();
+                match property.data::<Node>() {
+                    Some(node) => state.properties.insert(name.to_owned(), node),
+                    None => state.properties.remove(name),
+                };
+
+                let mut player: HashMap<&str, Value> = HashMap::new();
+                match name {
+                    "pause" | "idle-active" => {
+                        player.insert("PlaybackStatus", state.playback_status().into());
+                        player.insert("Metadata", state.metadata().into());
+                    }
+                    "duration" | "media-title" | "path" | "metadata" => {
+                        player.insert("Metadata", state.metadata().into());
+                    }
+                    "playlist-pos" | "playlist-count" => {
+                        player.insert("Metadata", state.metadata().into());
+                        player.insert("CanGoNext", state.can_go_next().into());
+                        player.insert("CanGoPrevious", state.can_go_previous().into());
+                    }
+                    "volume" => {
+                        player.insert("Volume", (state.f64("volume").unwrap_or(100.) / 100.).into());
+                    }
+                    "speed" => {
+                        player.insert("Rate", state.f64("speed").unwrap_or(1.).into());
+                    }
+                    "loop-file" | "loop-playlist" => {
+                        player.insert("LoopStatus", state.loop_status().into());
+                        player.insert("CanGoNext", state.can_go_next().into());
+                        player.insert("CanGoPrevious", state.can_go_previous().into());
+                    }
+                    "shuffle" => {
+                        player.insert("Shuffle", state.bool("shuffle").into());
+                    }
+                    "seekable" => {
+                        player.insert("CanSeek", state.bool("seekable").into());
+                    }
+                    "fullscreen" => {
+                        let root = HashMap::from([("Fullscreen", state.bool("fullscreen").into())]);
+                        properties_changed(connection, ROOT_INTERFACE, root);
+                    }
+                    _ => {}
+                }
+                drop(state);
+                properties_changed(connection, PLAYER_INTERFACE, player);
+            }
+            Event::PlaybackRestart => {
+                if let Ok(position) = observer.get_property::<f64>("time-pos") {
+                    let _ =
+                        connection.emit_signal(None::<()>, OBJECT_PATH, PLAYER_INTERFACE, "Seeked", &micros(position));
+                }
+            }
+            Event::Shutdown => break,
+            _ => {}
+        }
+    }
+    lock(&shared.control).take();
+}
@@ -8,7 +8,7 @@ use std::ffi::{c_char, CStr, CString};
 use std::ptr;
 use std::slice;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Node {
     None,
     String(String),
@@ -64,11 +64,29 @@ pub fn from_mpv_node(node: &mut mpv_node) -> Node {
     }
 }
 
+/// Convert a `Node` into a heap allocated `mpv_node` owned by Rust.
+///
+/// The returned node must be released with `free_mpv_node`, not with
+/// `mpv_free_node_contents()`.
 pub fn to_mpv_node(node: &Node) -> *mut mpv_node {
-    let mut mpv_node = Box::new(mpv_node {
+    Box::into_raw(Box::new(new_mpv_node(node)))
+}
+
+/// Release a node returned by `to_mpv_node`.
+///
+/// # Safety
+///
+/// `node` must come from `to_mpv_node` and must not be used afterwards.
+pub unsafe fn free_mpv_node(node: *mut mpv_node) {
+    let node = Box::from_raw(node);
+    free_mpv_node_contents(&node);
+}
+
+fn new_mpv_node(node: &Node) -> mpv_node {
+    let mut mpv_node = mpv_node {
         format: 0,
         u: mpv_node__bindgen_ty_1 { int64: 0 },
-    });
+    };
 
     match node {
         Node::None => {
@@ -93,7 +111,7 @@ pub fn to_mpv_node(node: &Node) -> *mut mpv_node {
         }
         Node::Array(arr) => {
             mpv_node.format = mpv_format_MPV_FORMAT_NODE_ARRAY;
-            let values: Vec<_> = arr.iter().map(to_mpv_node).collect();
+            let values: Vec<_> = arr.iter().map(new_mpv_node).collect();
             let list = Box::new(mpv_node_list {
                 num: values.len() as i32,
                 values: Box::into_raw(values.into_boxed_slice()) as *mut mpv_node,
@@ -107,7 +125,7 @@ pub fn to_mpv_node(node: &Node) -> *mut mpv_node {
                 .iter()
                 .map(|(k, v)| {
                     let ckey = CString::new(k.as_str()).expect("CString::new failed");
-                    (ckey.into_raw(), to_mpv_node(v))
+                    (ckey.into_raw(), new_mpv_node(v))
                 })
                 .unzip();
 
@@ -134,5 +152,26 @@ pub fn to_mpv_node(node: &Node) -> *mut mpv_node {
         }
     }
 
-    Box::into_raw(mpv_node)
+    mpv_node
+}
+
+unsafe fn free_mpv_node_contents(node: &mpv_node) {
+    match node.format {
+        mpv_format_MPV_FORMAT_STRING => drop(CString::from_raw(node.u.string)),
+        mpv_format_MPV_FORMAT_NODE_ARRAY | mpv_format_MPV_FORMAT_NODE_MAP => {
+            let list = Box::from_raw(node.u.list);
+            let num = list.num as usize;
+            let values = Box::from_raw(ptr::slice_from_raw_parts_mut(list.values, num));
+            values.iter().for_each(|value| free_mpv_node_contents(value));
+            if !list.keys.is_null() {
+                let keys = Box::from_raw(ptr::slice_from_raw_parts_mut(list.keys, num));
+                keys.iter().for_each(|&key| drop(CString::from_raw(key)));
+            }
+        }
+        mpv_format_MPV_FORMAT_BYTE_ARRAY => {
+            let ba = Box::from_raw(node.u.ba);
+            libc::free(ba.data);
+        }
+        _ => {}
+    }
 }
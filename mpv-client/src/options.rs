@@ -0,0 +1,234 @@
+//! Script options, like `mp.options` in Lua.
+//!
+//! Options are read, in order of priority, from the `script-opts` property
+//! (`--script-opts=<identifier>-<key>=<value>`), from the
+//! `script-opts/<identifier>.conf` file in the mpv configuration directory
+//! (`<key>=<value>` lines, `#` starts a comment), and from the defaults of the
+//! options type. The identifier defaults to the client name.
+//!
+//! ```ignore
+//! mpv_client::script_options! {
+//!     pub struct Options {
+//!         enabled: bool = true,
+//!         delay: f64 = 1.5,
+//!         label: String = String::from("hello"),
+//!     }
+//! }
+//!
+//! let options: Options = handle.read_options(None);
+//! ```
+
+use super::{Handle, Node, Result};
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::{Arc, Mutex};
+
+/// A type holding script options, usually implemented with
+/// `mpv_client::script_options!`.
+pub trait ScriptOptions: Default + Send + 'static {
+    /// Set the option `key` from its textual value.
+    fn set(&mut self, key: &str, value: &str) -> std::result::Result<(), String>;
+}
+
+/// A type that can be used as field of `mpv_client::script_options!`.
+pub trait OptionValue: Sized {
+    fn parse(value: &str) -> Option<Self>;
+}
+
+impl OptionValue for bool {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "yes" | "true" => Some(true),
+            "no" | "false" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+impl OptionValue for String {
+    fn parse(value: &str) -> Option<Self> {
+        Some(value.to_owned())
+    }
+}
+
+macro_rules! option_value_from_str {
+    ($($type:ty),+) => {
+        $(impl OptionValue for $type {
+            fn parse(value: &str) -> Option<Self> {
+                value.trim().parse().ok()
+            }
+        })+
+    };
+}
+
+option_value_from_str!(i8, i16, i32, i64, u8, u16, u32, u64, usize, isize, f32, f64, char);
+
+/// Declare a struct of script options with their default values, and implement
+/// `ScriptOptions` for it. The field types must implement `OptionValue`.
+///
+/// Option names are the field names with `_` replaced by `-`.
+#[macro_export]
+macro_rules! script_options {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$field_meta:meta])* $field:ident: $type:ty = $default:expr),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$field_meta])* pub $field: $type,)*
+        }
+
+        impl ::std::default::Default for $name {
+            fn default() -> Self {
+                Self {
+                    $($field: $default,)*
+                }
+            }
+        }
+
+        impl $crate::options::ScriptOptions for $name {
+            fn set(&mut self, key: &str, value: &str) -> ::std::result::Result<(), ::std::string::String> {
+                $(if key == stringify!($field).replace('_', "-") {
+                    return match <$type as $crate::options::OptionValue>::parse(value) {
+                        Some(value) => {
+                            self.$field = value;
+                            Ok(())
+                        }
+                        None => Err(format!("invalid value '{}'", value)),
+                    };
+                })*
+                Err(::std::string::String::from("unknown option"))
+            }
+        }
+    };
+}
+
+/// Options of the conf file and of the `script-opts` property.
+struct Sources {
+    identifier: String,
+    file: Vec<(String, String)>,
+    script_opts: HashMap<String, String>,
+}
+
+impl Sources {
+    fn read(handle: &mut Handle, identifier: Option<&str>) -> Self {
+        let identifier = identifier.unwrap_or_else(|| handle.name()).to_owned();
+        let file = handle.read_options_file(&identifier);
+        let mut sources = Self {
+            identifier,
+            file,
+            script_opts: HashMap::new(),
+        };
+        sources.script_opts = sources.read_script_opts(handle);
+        sources
+    }
+
+    /// The options of the `script-opts` property for this identifier.
+    fn read_script_opts(&self, handle: &mut Handle) -> HashMap<String, String> {
+        let prefix = format!("{}-", self.identifier);
+        match handle.get_property::<Node>("script-opts") {
+            Ok(Node::Map(map)) => map
+                .into_iter()
+                .filter_map(|(key, value)| match (key.strip_prefix(&prefix), value) {
+                    (Some(key), Node::String(value)) => Some((key.to_owned(), value)),
+                    _ => None,
+                })
+                .collect(),
+            _ => HashMap::new(),
+        }
+    }
+
+    fn apply<T: ScriptOptions>(&self, handle: &mut Handle) -> T {
+        let mut options = T::default();
+        let script_opts = self
+            .script_opts
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str(), "script-opts"));
+        let file = self.file.iter().map(|(k, v)| (k.as_str(), v.as_str(), "conf file"));
+        for (key, value, source) in file.chain(script_opts) {
+            if let Err(e) = options.set(key, value) {
+                handle.print_warning(format!("{} option '{}' ({}): {}", self.identifier, key, source, e));
+            }
+        }
+        options
+    }
+}
+
+impl Handle {
+    /// Read the options of `identifier` (or of the client name when `None`)
+    /// into `T`, like `mp.options.read_options` in Lua. Unknown options and
+    /// invalid values are reported with a warning on the terminal.
+    pub fn read_options<T: ScriptOptions>(&mut self, identifier: Option<&str>) -> T {
+        Sources::read(self, identifier).apply(self)
+    }
+
+    /// Same as `Handle::read_options`, but keep the options up to date when the
+    /// `script-opts` property changes at runtime. `on_update` is then called
+    /// with the new options and the names of the options that changed, right
+    /// before they are stored in the returned `Arc`.
+    ///
+    /// The update is run by `Handle::wait_event`.
+    pub fn watch_options<T, F>(&mut self, identifier: Option<&str>, mut on_update: F) -> Result<Arc<Mutex<T>>>
+    where
+        T: ScriptOptions,
+        F: FnMut(&mut Handle, &T, &[String]) + Send + 'static,
+    {
+        let mut sources = Sources::read(self, identifier);
+        let options = Arc::new(Mutex::new(sources.apply::<T>(self)));
+
+        let shared = options.clone();
+        self.observe_property_callback::<Node, _>("script-opts", move |handle, _| {
+            let script_opts = sources.read_script_opts(handle);
+            let mut changed: Vec<String> = script_opts
+                .iter()
+                .filter(|(key, value)| sources.script_opts.get(*key) != Some(*value))
+                .map(|(key, _)| key.clone())
+                .chain(
+                    sources
+                        .script_opts
+                        .keys()
+                        .filter(|key| !script_opts.contains_key(*key))
+                        .cloned(),
+                )
+                .collect();
+            if changed.is_empty() {
+                return;
+            }
+            changed.sort();
+
+            sources.script_opts = script_opts;
+            let options = sources.apply::<T>(handle);
+            on_update(handle, &options, &changed);
+            *shared.lock().unwrap_or_else(|e| e.into_inner()) = options;
+        })?;
+
+        Ok(options)
+    }
+
+    /// Read `script-opts/<identifier>.conf` from the configuration directory.
+    fn read_options_file(&mut self, identifier: &str) -> Vec<(String, String)> {
+        let path = match self.command_ret(["expand-path", &format!("~~/script-opts/{}.conf", identifier)]) {
+            Ok(Node::String(path)) => path,
+            _ => return Vec::new(),
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Vec::new();
+        };
+
+        let mut options = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) => options.push((key.trim_end().to_owned(), value.trim_start().to_owned())),
+                None => self.print_warning(format!("{}:{}: expected key=value", path, i + 1)),
+            }
+        }
+        options
+    }
+}
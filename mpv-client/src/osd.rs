@@ -0,0 +1,537 @@
+//! OSD overlays drawn with ASS, using the `osd-overlay` command.
+//!
+//! Every overlay is rendered by a closure returning ASS events, which is called
+//! again whenever the OSD is resized, so it can lay out its content for the
+//! current `OsdDimensions`. Coordinates are in OSD pixels.
+
+use super::dispatch::{self, HandleRef};
+use super::{mpv_error_MPV_ERROR_INVALID_PARAMETER, Error, Handle, Node, Result};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// The token of the `BitmapOverlay` which last added each `overlay-add` id,
+/// so dropping an overlay doesn't remove a newer one with the same id.
+static BITMAP_OWNERS: Mutex<[u64; 64]> = Mutex::new([0; 64]);
+static NEXT_BITMAP_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) type Render = Arc<Mutex<dyn FnMut(&OsdDimensions) -> String + Send>>;
+
+/// Size and margins of the OSD, from the `osd-dimensions` property.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct OsdDimensions {
+    /// Width of the OSD in pixels.
+    pub w: i64,
+    /// Height of the OSD in pixels.
+    pub h: i64,
+    /// Pixel aspect ratio of the OSD.
+    pub par: f64,
+    /// Display aspect ratio of the OSD.
+    pub aspect: f64,
+    /// Top margin, i.e. black bar, in pixels.
+    pub mt: i64,
+    /// Bottom margin in pixels.
+    pub mb: i64,
+    /// Left margin in pixels.
+    pub ml: i64,
+    /// Right margin in pixels.
+    pub mr: i64,
+}
+
+/// An overlay created with `Handle::add_overlay`. It is removed from the
+/// screen when dropped. The overlay can outlive the client, it then does
+/// nothing.
+pub struct Overlay {
+    id: i64,
+    handle: HandleRef,
+}
+
+/// A raw BGRA image shown with `overlay-add`, created with
+/// `Handle::add_bitmap_overlay`. It owns the pixels mpv reads from, and is
+/// removed from the screen when dropped.
+///
+/// `overlay-add` ids are shared by every client of the player, they range from
+/// 0 to 63.
+pub struct BitmapOverlay {
+    id: u8,
+    /// Identifies the overlay in `BITMAP_OWNERS`.
+    token: u64,
+    handle: HandleRef,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    display_size: Option<(u32, u32)>,
+    pixels: Box<[u8]>,
+}
+
+pub(crate) struct OverlayEntry {
+    z: i64,
+    hidden: bool,
+    render: Render,
+}
+
+/// Builder of ASS events, similar to `mp.assdraw` in Lua.
+///
+/// Drawing coordinates are scaled like `mp.assdraw` does (`\p4`), so they can
+/// have sub-pixel precision.
+#[derive(Debug, Default, Clone)]
+pub struct AssBuilder {
+    text: String,
+    drawing: bool,
+}
+
+impl OsdDimensions {
+    fn from_node(node: Node) -> Option<Self> {
+        let Node::Map(map) = node else {
+            return None;
+        };
+        let int = |key: &str| match map.get(key) {
+            Some(Node::Int(value)) => *value,
+            Some(Node::Double(value)) => *value as i64,
+            _ => 0,
+        };
+        let double = |key: &str| match map.get(key) {
+            Some(Node::Double(value)) => *value,
+            Some(Node::Int(value)) => *value as f64,
+            _ => 0.,
+        };
+        Some(Self {
+            w: int("w"),
+            h: int("h"),
+            par: double("par"),
+            aspect: double("aspect"),
+            mt: int("mt"),
+            mb: int("mb"),
+            ml: int("ml"),
+            mr: int("mr"),
+        })
+    }
+}
+
+/// Escape text so it is displayed as is by ASS, like `ass_escape` in mpv's
+/// console.lua.
+pub fn ass_escape(text: &str) -> String {
+    let text = text
+        .replace('\\', "\\\u{feff}")
+        .replace('{', "\\{")
+        .replace('}', "\\}")
+        // Precede newlines with a ZWNBSP to prevent ASS from collapsing them.
+        .replace('\n', "\u{feff}\\N")
+        // Turn leading spaces into hard spaces so ASS doesn't strip them.
+        .replace("\\N ", "\\N\\h");
+    match text.strip_prefix(' ') {
+        Some(text) => format!("\\h{}", text),
+        None => text,
+    }
+}
+
+impl AssBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a new event (line). Styles don't carry over to the new event.
+    pub fn new_event(&mut self) -> &mut Self {
+        if !self.text.is_empty() {
+            self.text.push('\n');
+        }
+        self.drawing = false;
+        self
+    }
+
+    /// Append raw ASS, e.g. override tags.
+    pub fn append(&mut self, ass: &str) -> &mut Self {
+        self.text.push_str(ass);
+        self
+    }
+
+    /// Append user text, escaped with `ass_escape`.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        self.append(&ass_escape(text))
+    }
+
+    /// Set the position of the event (`\pos`).
+    pub fn pos(&mut self, x: f64, y: f64) -> &mut Self {
+        self.tag(format_args!("\\pos({},{})", x, y))
+    }
+
+    /// Set the alignment of the event (`\an`), with the numpad layout: 7 is
+    /// top-left, 5 is centered, 3 is bottom-right.
+    pub fn an(&mut self, alignment: u8) -> &mut Self {
+        self.tag(format_args!("\\an{}", alignment))
+    }
+
+    /// Set the font size (`\fs`).
+    pub fn font_size(&mut self, size: f64) -> &mut Self {
+        self.tag(format_args!("\\fs{}", size))
+    }
+
+    /// Set the font (`\fn`).
+    pub fn font(&mut self, name: &str) -> &mut Self {
+        self.tag(format_args!("\\fn{}", name))
+    }
+
+    /// Set the primary color (`\1c`), given as `0xRRGGBB`.
+    pub fn color(&mut self, rgb: u32) -> &mut Self {
+        self.tag(format_args!("\\1c&H{}&", bgr(rgb)))
+    }
+
+    /// Set the border color (`\3c`), given as `0xRRGGBB`.
+    pub fn border_color(&mut self, rgb: u32) -> &mut Self {
+        self.tag(format_args!("\\3c&H{}&", bgr(rgb)))
+    }
+
+    /// Set the transparency of every part (`\alpha`), 0 is opaque and 255 is
+    /// fully transparent.
+    pub fn alpha(&mut self, alpha: u8) -> &mut Self {
+        self.tag(format_args!("\\alpha&H{:02X}&", alpha))
+    }
+
+    /// Enable or disable bold text (`\b`).
+    pub fn bold(&mut self, bold: bool) -> &mut Self {
+        self.tag(format_args!("\\b{}", bold as u8))
+    }
+
+    /// Enable or disable italic text (`\i`).
+    pub fn italic(&mut self, italic: bool) -> &mut Self {
+        self.tag(format_args!("\\i{}", italic as u8))
+    }
+
+    /// Set the border width (`\bord`).
+    pub fn border(&mut self, width: f64) -> &mut Self {
+        self.tag(format_args!("\\bord{}", width))
+    }
+
+    /// Set the shadow depth (`\shad`).
+    pub fn shadow(&mut self, depth: f64) -> &mut Self {
+        self.tag(format_args!("\\shad{}", depth))
+    }
+
+    /// Clip the event to a rectangle (`\clip`).
+    pub fn clip(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) -> &mut Self {
+        self.tag(format_args!("\\clip({},{},{},{})", x0, y0, x1, y1))
+    }
+
+    /// Start vector drawing mode.
+    pub fn draw_start(&mut self) -> &mut Self {
+        self.drawing = true;
+        self.append("{\\p4}")
+    }
+
+    /// Stop vector drawing mode.
+    pub fn draw_stop(&mut self) -> &mut Self {
+        self.drawing = false;
+        self.append("{\\p0}")
+    }
+
+    /// Move the drawing cursor without drawing.
+    pub fn move_to(&mut self, x: f64, y: f64) -> &mut Self {
+        self.command('m', &[(x, y)])
+    }
+
+    /// Draw a line from the drawing cursor.
+    pub fn line_to(&mut self, x: f64, y: f64) -> &mut Self {
+        self.command('l', &[(x, y)])
+    }
+
+    /// Draw a cubic bezier curve from the drawing cursor.
+    pub fn bezier_curve(&mut self, x1: f64, y1: f64, x2: f64, y2: f64, x3: f64, y3: f64) -> &mut Self {
+        self.command('b', &[(x1, y1), (x2, y2), (x3, y3)])
+    }
+
+    /// Draw a rectangle, clockwise.
+    pub fn rect_cw(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) -> &mut Self {
+        self.move_to(x0, y0).line_to(x1, y0).line_to(x1, y1).line_to(x0, y1)
+    }
+
+    /// Draw a rectangle, counterclockwise. Can be used to cut out a clockwise
+    /// rectangle.
+    pub fn rect_ccw(&mut self, x0: f64, y0: f64, x1: f64, y1: f64) -> &mut Self {
+        self.move_to(x0, y0).line_to(x0, y1).line_to(x1, y1).line_to(x1, y0)
+    }
+
+    /// The ASS events built so far.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    fn tag(&mut self, tag: fmt::Arguments) -> &mut Self {
+        let _ = write!(self.text, "{{{}}}", tag);
+        self
+    }
+
+    fn command(&mut self, command: char, points: &[(f64, f64)]) -> &mut Self {
+        if !self.drawing {
+            self.draw_start();
+        }
+        let _ = write!(self.text, " {}", command);
+        for (x, y) in points {
+            let _ = write!(self.text, " {} {}", (x * 8.).round() as i64, (y * 8.).round() as i64);
+        }
+        self
+    }
+}
+
+fn bgr(rgb: u32) -> String {
+    format!("{:02X}{:02X}{:02X}", rgb & 0xff, (rgb >> 8) & 0xff, (rgb >> 16) & 0xff)
+}
+
+impl fmt::Display for AssBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl From<AssBuilder> for String {
+    fn from(ass: AssBuilder) -> Self {
+        ass.text
+    }
+}
+
+impl Overlay {
+    /// The `osd-overlay` id of the overlay.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Render the overlay again, e.g. after the state it displays changed.
+    pub fn update(&self) -> Result<()> {
+        self.handle
+            .with(|handle| handle.render_overlay(self.id))
+            .unwrap_or(Ok(()))
+    }
+
+    /// Change the z-order of the overlay. Overlays with a higher z are drawn on
+    /// top of overlays with a lower z.
+    pub fn set_z(&self, z: i64) -> Result<()> {
+        self.handle
+            .with(|handle| {
+                dispatch::with_overlay(unsafe { handle.as_ptr() }, self.id, |overlay| overlay.z = z);
+                handle.render_overlay(self.id)
+            })
+            .unwrap_or(Ok(()))
+    }
+
+    /// Hide or show the overlay.
+    pub fn set_hidden(&self, hidden: bool) -> Result<()> {
+        self.handle
+            .with(|handle| {
+                dispatch::with_overlay(unsafe { handle.as_ptr() }, self.id, |overlay| overlay.hidden = hidden);
+                handle.render_overlay(self.id)
+            })
+            .unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.handle.with(|handle| {
+            let entry = dispatch::remove_overlay(unsafe { handle.as_ptr() }, self.id);
+            drop(entry);
+            let mut args = HashMap::new();
+            args.insert("name".to_owned(), Node::String("osd-overlay".to_owned()));
+            args.insert("id".to_owned(), Node::Int(self.id));
+            args.insert("format".to_owned(), Node::String("none".to_owned()));
+            let _ = handle.command_node(Node::Map(args));
+        });
+    }
+}
+
+impl Handle {
+    /// Add an ASS overlay drawn with the z-order `z`. `render` returns the ASS
+    /// events of the overlay (see `AssBuilder`) for the OSD size, it is called
+    /// now, on `Overlay::update`, and whenever the OSD is resized.
+    ///
+    /// Resizes are handled by `Handle::wait_event`.
+    pub fn add_overlay<F>(&mut self, z: i64, render: F) -> Result<Overlay>
+    where
+        F: FnMut(&OsdDimensions) -> String + Send + 'static,
+    {
+        let handle = unsafe { self.as_mut_ptr() };
+        if dispatch::osd_dimensions(handle).is_none() {
+            let dimensions = self
+                .get_property::<Node>("osd-dimensions")
+                .ok()
+                .and_then(OsdDimensions::from_node);
+            dispatch::set_osd_dimensions(handle, dimensions.unwrap_or_default());
+            self.observe_property_callback::<Node, _>("osd-dimensions", |handle, property| {
+                let Some(dimensions) = property.data::<Node>().and_then(OsdDimensions::from_node) else {
+                    return;
+                };
+                let handle_ptr = unsafe { handle.as_ptr() };
+                if dispatch::osd_dimensions(handle_ptr) != Some(dimensions) {
+                    dispatch::set_osd_dimensions(handle_ptr, dimensions);
+                    for id in dispatch::overlay_ids(handle_ptr) {
+                        let _ = handle.render_overlay(id);
+                    }
+                }
+            })?;
+        }
+
+        let entry = OverlayEntry {
+            z,
+            hidden: false,
+            render: Arc::new(Mutex::new(render)),
+        };
+        let overlay = Overlay {
+            id: dispatch::add_overlay(handle, entry),
+            handle: HandleRef::new(self),
+        };
+        self.render_overlay(overlay.id)?;
+        Ok(overlay)
+    }
+
+    /// Return the last known OSD dimensions, tracked once an overlay was added.
+    pub fn osd_dimensions(&mut self) -> Option<OsdDimensions> {
+        dispatch::osd_dimensions(unsafe { self.as_ptr() })
+    }
+
+    pub(crate) fn render_overlay(&mut self, id: i64) -> Result<()> {
+        let handle = unsafe { self.as_ptr() };
+        let Some((z, hidden, render)) = dispatch::with_overlay(handle, id, |overlay| {
+            (overlay.z, overlay.hidden, overlay.render.clone())
+        }) else {
+            return Ok(());
+        };
+        let dimensions = dispatch::osd_dimensions(handle).unwrap_or_default();
+        let data = render.lock().unwrap_or_else(|e| e.into_inner())(&dimensions);
+
+        let mut args = HashMap::new();
+        args.insert("name".to_owned(), Node::String("osd-overlay".to_owned()));
+        args.insert("id".to_owned(), Node::Int(id));
+        args.insert("format".to_owned(), Node::String("ass-events".to_owned()));
+        args.insert("data".to_owned(), Node::String(data));
+        args.insert("res_x".to_owned(), Node::Int(dimensions.w));
+        args.insert("res_y".to_owned(), Node::Int(dimensions.h));
+        args.insert("z".to_owned(), Node::Int(z));
+        args.insert("hidden".to_owned(), Node::Bool(hidden));
+        self.command_node(Node::Map(args)).map(|_| ())
+    }
+}
+
+impl BitmapOverlay {
+    /// The `overlay-add` id of the overlay.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels of the image, 4 bytes per pixel in B, G, R, A order, with
+    /// premultiplied alpha. Call `BitmapOverlay::update` to show the changes.
+    pub fn pixels_mut(&mut self) -> &mut [u8] {
+        &mut self.pixels
+    }
+
+    /// Move the top-left corner of the overlay to `x`, `y`, in OSD pixels.
+    pub fn set_position(&mut self, x: i32, y: i32) -> Result<()> {
+        self.x = x;
+        self.y = y;
+        self.update()
+    }
+
+    /// Scale the image to `width`x`height` OSD pixels, or show it at its own
+    /// size with `None`.
+    pub fn set_display_size(&mut self, size: Option<(u32, u32)>) -> Result<()> {
+        self.display_size = size;
+        self.update()
+    }
+
+    /// Show the overlay again with the current pixels, position and size. It
+    /// replaces any overlay added with the same id since. Does nothing once
+    /// the client is destroyed.
+    pub fn update(&self) -> Result<()> {
+        self.handle.with(|handle| self.add(handle)).unwrap_or(Ok(()))
+    }
+
+    fn add(&self, handle: &mut Handle) -> Result<()> {
+        let (dw, dh) = self.display_size.unwrap_or((self.width, self.height));
+        let mut owners = bitmap_owners();
+        handle.command([
+            "overlay-add".to_owned(),
+            self.id.to_string(),
+            self.x.to_string(),
+            self.y.to_string(),
+            format!("&{}", self.pixels.as_ptr() as usize),
+            "0".to_owned(),
+            "bgra".to_owned(),
+            self.width.to_string(),
+            self.height.to_string(),
+            (self.width * 4).to_string(),
+            dw.to_string(),
+            dh.to_string(),
+        ])?;
+        owners[self.id as usize] = self.token;
+        Ok(())
+    }
+}
+
+fn bitmap_owners() -> MutexGuard<'static, [u64; 64]> {
+    BITMAP_OWNERS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl Drop for BitmapOverlay {
+    fn drop(&mut self) {
+        let mut owners = bitmap_owners();
+        if owners[self.id as usize] != self.token {
+            // Replaced by another overlay, mpv doesn't read the pixels anymore.
+            return;
+        }
+        owners[self.id as usize] = 0;
+        // mpv stops reading the pixels once the overlay is removed. Without the
+        // client it can't be removed, so the pixels are leaked instead.
+        let removed = self
+            .handle
+            .with(|handle| handle.command(["overlay-remove", &self.id.to_string()]));
+        if removed.is_none() {
+            std::mem::forget(std::mem::take(&mut self.pixels));
+        }
+    }
+}
+
+impl Handle {
+    /// Show a `width`x`height` BGRA image (see `BitmapOverlay::pixels_mut`)
+    /// with its top-left corner at `x`, `y`, with `overlay-add`. `id` is the
+    /// overlay id (0 to 63), adding an overlay with the id of an existing one
+    /// replaces it. Dropping the replaced overlay then doesn't remove the new
+    /// one.
+    ///
+    /// Fails with `MPV_ERROR_INVALID_PARAMETER` if `pixels` isn't
+    /// `width * height * 4` bytes long.
+    pub fn add_bitmap_overlay(
+        &mut self,
+        id: u8,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    ) -> Result<BitmapOverlay> {
+        if id > 63 || pixels.len() as u64 != width as u64 * height as u64 * 4 {
+            return Err(Error::new(mpv_error_MPV_ERROR_INVALID_PARAMETER));
+        }
+        let overlay = BitmapOverlay {
+            id,
+            token: NEXT_BITMAP_TOKEN.fetch_add(1, Ordering::Relaxed),
+            handle: HandleRef::new(self),
+            x,
+            y,
+            width,
+            height,
+            display_size: None,
+            pixels: pixels.into_boxed_slice(),
+        };
+        overlay.add(self)?;
+        Ok(overlay)
+    }
+}
@@ -0,0 +1,226 @@
+use super::node::{free_mpv_node, to_mpv_node};
+use super::{
+    mpv_event_client_message, mpv_event_command, mpv_event_hook, mpv_event_log_message, mpv_event_property, mpv_format,
+    mpv_format_MPV_FORMAT_DOUBLE, mpv_format_MPV_FORMAT_FLAG, mpv_format_MPV_FORMAT_INT64, mpv_format_MPV_FORMAT_NODE,
+    mpv_format_MPV_FORMAT_NONE, mpv_format_MPV_FORMAT_STRING, mpv_log_level, mpv_node, Node,
+};
+
+use std::ffi::{c_char, c_int, c_void, CString};
+use std::ptr;
+
+/// A property value allocated by Rust, laid out like mpv's event data.
+pub(crate) enum Value {
+    None,
+    String(CString),
+    Flag(c_int),
+    Int64(i64),
+    Double(f64),
+    Node(*mut mpv_node),
+}
+
+/// A `mpv_event_property` allocated by Rust, for events that were not
+/// returned by `mpv_wait_event()`.
+pub(crate) struct OwnedProperty {
+    pub(crate) raw: mpv_event_property,
+    name: CString,
+    value: Value,
+    string: *const c_char,
+}
+
+/// A `mpv_event_log_message` allocated by Rust.
+pub(crate) struct OwnedLogMessage {
+    pub(crate) raw: mpv_event_log_message,
+    _prefix: CString,
+    _level: CString,
+    _text: CString,
+}
+
+/// A `mpv_event_client_message` allocated by Rust.
+pub(crate) struct OwnedClientMessage {
+    pub(crate) raw: mpv_event_client_message,
+    _args: Vec<CString>,
+    _ptrs: Vec<*const c_char>,
+}
+
+/// A `mpv_event_hook` allocated by Rust.
+pub(crate) struct OwnedHook {
+    pub(crate) raw: mpv_event_hook,
+    _name: CString,
+}
+
+/// A `mpv_event_command` allocated by Rust.
+pub(crate) struct OwnedCommandResult {
+    pub(crate) raw: mpv_event_command,
+    node: *mut mpv_node,
+}
+
+fn c_string(value: &str) -> CString {
+    CString::new(value).unwrap_or_default()
+}
+
+impl Value {
+    /// The value of a node in its own format: `Node::String` as a string,
+    /// `Node::Bool` as a flag, etc. Arrays and maps stay nodes.
+    pub(crate) fn from_node(node: &Node) -> Self {
+        match node {
+            Node::None => Self::None,
+            Node::String(value) => Self::string(value.clone()),
+            Node::Bool(value) => Self::Flag(*value as c_int),
+            Node::Int(value) => Self::Int64(*value),
+            Node::Double(value) => Self::Double(*value),
+            node => Self::node(node),
+        }
+    }
+
+    /// The value of a node converted to `format`, like mpv converts property
+    /// values to the format requested by the client.
+    pub(crate) fn convert(node: &Node, format: mpv_format) -> Option<Self> {
+        match (format, node) {
+            (mpv_format_MPV_FORMAT_NONE, _) => Some(Self::None),
+            (mpv_format_MPV_FORMAT_NODE, node) => Some(Self::node(node)),
+            (mpv_format_MPV_FORMAT_STRING, Node::String(value)) => Some(Self::string(value.clone())),
+            (mpv_format_MPV_FORMAT_STRING, Node::Bool(value)) => {
+                Some(Self::string(String::from(if *value { "yes" } else { "no" })))
+            }
+            (mpv_format_MPV_FORMAT_STRING, Node::Int(value)) => Some(Self::string(value.to_string())),
+            (mpv_format_MPV_FORMAT_STRING, Node::Double(value)) => Some(Self::string(format!("{:.6}", value))),
+            (mpv_format_MPV_FORMAT_FLAG, Node::Bool(value)) => Some(Self::Flag(*value as c_int)),
+            (mpv_format_MPV_FORMAT_INT64, Node::Int(value)) => Some(Self::Int64(*value)),
+            // Truncated like libmpv does, if in range
+            (mpv_format_MPV_FORMAT_INT64, Node::Double(value))
+                if *value > i64::MIN as f64 && *value < i64::MAX as f64 =>
+            {
+                Some(Self::Int64(*value as i64))
+            }
+            (mpv_format_MPV_FORMAT_DOUBLE, Node::Double(value)) => Some(Self::Double(*value)),
+            (mpv_format_MPV_FORMAT_DOUBLE, Node::Int(value)) => Some(Self::Double(*value as f64)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn string(value: String) -> Self {
+        Self::String(CString::new(value).unwrap_or_default())
+    }
+
+    pub(crate) fn node(value: &Node) -> Self {
+        Self::Node(to_mpv_node(value))
+    }
+
+    fn format(&self) -> mpv_format {
+        match self {
+            Self::None => mpv_format_MPV_FORMAT_NONE,
+            Self::String(..) => mpv_format_MPV_FORMAT_STRING,
+            Self::Flag(..) => mpv_format_MPV_FORMAT_FLAG,
+            Self::Int64(..) => mpv_format_MPV_FORMAT_INT64,
+            Self::Double(..) => mpv_format_MPV_FORMAT_DOUBLE,
+            Self::Node(..) => mpv_format_MPV_FORMAT_NODE,
+        }
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut c_void {
+        match self {
+            Self::None | Self::String(..) => ptr::null_mut(),
+            Self::Flag(flag) => flag as *mut c_int as *mut c_void,
+            Self::Int64(int) => int as *mut i64 as *mut c_void,
+            Self::Double(double) => double as *mut f64 as *mut c_void,
+            Self::Node(node) => *node as *mut c_void,
+        }
+    }
+}
+
+impl Drop for Value {
+    fn drop(&mut self) {
+        if let Self::Node(node) = *self {
+            unsafe { free_mpv_node(node) }
+        }
+    }
+}
+
+impl OwnedProperty {
+    pub(crate) fn new(name: &str, value: Value) -> Box<Self> {
+        let mut property = Box::new(Self {
+            raw: mpv_event_property {
+                name: ptr::null(),
+                format: value.format(),
+                data: ptr::null_mut(),
+            },
+            name: CString::new(name).unwrap_or_default(),
+            value,
+            string: ptr::null(),
+        });
+        // The box keeps the addresses stable, the raw struct can point into it.
+        let p = &mut *property;
+        p.raw.name = p.name.as_ptr();
+        p.raw.data = match &mut p.value {
+            Value::String(string) => {
+                p.string = string.as_ptr();
+                &mut p.string as *mut *const c_char as *mut c_void
+            }
+            value => value.as_mut_ptr(),
+        };
+        property
+    }
+}
+
+impl OwnedLogMessage {
+    pub(crate) fn new(prefix: &str, level: &str, text: &str, log_level: mpv_log_level) -> Box<Self> {
+        let (prefix, level, text) = (c_string(prefix), c_string(level), c_string(text));
+        Box::new(Self {
+            raw: mpv_event_log_message {
+                prefix: prefix.as_ptr(),
+                level: level.as_ptr(),
+                text: text.as_ptr(),
+                log_level,
+            },
+            _prefix: prefix,
+            _level: level,
+            _text: text,
+        })
+    }
+}
+
+impl OwnedClientMessage {
+    pub(crate) fn new<S: AsRef<str>>(args: &[S]) -> Box<Self> {
+        let args: Vec<CString> = args.iter().map(|arg| c_string(arg.as_ref())).collect();
+        let mut ptrs: Vec<*const c_char> = args.iter().map(|arg| arg.as_ptr()).collect();
+        Box::new(Self {
+            raw: mpv_event_client_message {
+                num_args: ptrs.len() as c_int,
+                args: ptrs.as_mut_ptr(),
+            },
+            _args: args,
+            _ptrs: ptrs,
+        })
+    }
+}
+
+impl OwnedHook {
+    pub(crate) fn new(name: &str, id: u64) -> Box<Self> {
+        let name = c_string(name);
+        Box::new(Self {
+            raw: mpv_event_hook {
+                name: name.as_ptr(),
+                id,
+            },
+            _name: name,
+        })
+    }
+}
+
+impl OwnedCommandResult {
+    pub(crate) fn new(result: &Node) -> Box<Self> {
+        let node = to_mpv_node(result);
+        // The raw struct shares the contents of the node, which are released
+        // with it on drop.
+        let raw = mpv_event_command {
+            result: unsafe { ptr::read(node) },
+        };
+        Box::new(Self { raw, node })
+    }
+}
+
+impl Drop for OwnedCommandResult {
+    fn drop(&mut self) {
+        unsafe { free_mpv_node(self.node) }
+    }
+}
@@ -0,0 +1,177 @@
+use super::dispatch::{self, HandleRef};
+use super::{mpv_abort_async_command, CommandResult, Handle, Node, Result};
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+type Callback = Box<dyn FnOnce(&mut Handle, Result<Node>) + Send>;
+
+/// An asynchronous command started with `Handle::command_async_pending` or
+/// `Handle::command_node_async_pending`.
+///
+/// It resolves when the `CommandReply` event of the command is received by
+/// `Handle::wait_event`, which consumes the event. The result can then be
+/// read with `PendingCommand::try_result`, by awaiting the token, or with a
+/// callback set with `PendingCommand::on_reply`.
+///
+/// The token can outlive the client, it then can't be resolved or aborted
+/// anymore.
+pub struct PendingCommand {
+    reply: u64,
+    handle: HandleRef,
+    slot: Arc<Mutex<Slot>>,
+    abort_on_drop: bool,
+}
+
+/// The state shared by a `PendingCommand` and the dispatcher.
+#[derive(Default)]
+pub(crate) struct Slot {
+    result: Option<Result<Node>>,
+    done: bool,
+    callback: Option<Callback>,
+    waker: Option<Waker>,
+}
+
+pub(crate) type SharedSlot = Arc<Mutex<Slot>>;
+
+fn lock(slot: &SharedSlot) -> MutexGuard<'_, Slot> {
+    slot.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+impl PendingCommand {
+    /// The `reply_userdata` of the command.
+    pub fn reply(&self) -> u64 {
+        self.reply
+    }
+
+    /// Whether the `CommandReply` event was received.
+    pub fn is_done(&self) -> bool {
+        lock(&self.slot).done
+    }
+
+    /// Take the result of the command, if it is done and the result wasn't
+    /// taken yet.
+    pub fn try_result(&self) -> Option<Result<Node>> {
+        lock(&self.slot).result.take()
+    }
+
+    /// Ask mpv to abort the command, see `Handle::abort_async_command`. The
+    /// token still resolves, usually with an error.
+    pub fn abort(&self) {
+        if !self.is_done() {
+            self.handle
+                .with(|handle| unsafe { mpv_abort_async_command(handle.as_mut_ptr(), self.reply) });
+        }
+    }
+
+    /// Abort the command if the token is dropped before it's done. Disabled by
+    /// default, the command then runs to completion and its result is
+    /// discarded.
+    pub fn abort_on_drop(mut self, abort: bool) -> Self {
+        self.abort_on_drop = abort;
+        self
+    }
+
+    /// Call `callback` with the result when the command is done, or right now
+    /// if it is already done and the result wasn't taken. The callback is
+    /// called even if the token is dropped before, but not once the client is
+    /// destroyed.
+    pub fn on_reply<F>(&self, callback: F)
+    where
+        F: FnOnce(&mut Handle, Result<Node>) + Send + 'static,
+    {
+        let mut slot = lock(&self.slot);
+        match slot.result.take() {
+            Some(result) => {
+                drop(slot);
+                self.handle.with(|handle| callback(handle, result));
+            }
+            None if !slot.done => slot.callback = Some(Box::new(callback)),
+            None => {}
+        }
+    }
+}
+
+impl Future for PendingCommand {
+    type Output = Result<Node>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut slot = lock(&self.slot);
+        match slot.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                slot.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for PendingCommand {
+    fn drop(&mut self) {
+        if self.abort_on_drop {
+            self.abort();
+        }
+    }
+}
+
+impl Handle {
+    /// Same as `Handle::command_async`, but return a `PendingCommand` instead
+    /// of taking a reply number.
+    pub fn command_async_pending<I, S>(&mut self, args: I) -> Result<PendingCommand>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let args: Vec<Node> = args.into_iter().map(|s| Node::String(s.as_ref().to_owned())).collect();
+        self.command_node_async_pending(Node::Array(args))
+    }
+
+    /// Same as `Handle::command_node_async`, but return a `PendingCommand`
+    /// instead of taking a reply number.
+    pub fn command_node_async_pending(&mut self, args: Node) -> Result<PendingCommand> {
+        let handle = unsafe { self.as_ptr() };
+        let slot = SharedSlot::default();
+        let reply = dispatch::add_pending_command(handle, slot.clone());
+        if let Err(e) = self.command_node_async(reply, args) {
+            drop(dispatch::take_pending_command(handle, reply));
+            return Err(e);
+        }
+        Ok(PendingCommand {
+            reply,
+            handle: HandleRef::new(self),
+            slot,
+            abort_on_drop: false,
+        })
+    }
+
+    /// Resolve the `PendingCommand` of a `CommandReply` event. Return whether
+    /// the event was handled.
+    pub(crate) fn dispatch_command_reply(&mut self, result: &Result<CommandResult>, reply: u64) -> bool {
+        let Some(slot) = dispatch::take_pending_command(unsafe { self.as_ptr() }, reply) else {
+            return false;
+        };
+        let result = match result {
+            Ok(command) => Ok(command.result()),
+            Err(e) => Err(*e),
+        };
+
+        let mut state = lock(&slot);
+        state.done = true;
+        match state.callback.take() {
+            Some(callback) => {
+                drop(state);
+                callback(self, result);
+            }
+            None => {
+                state.result = Some(result);
+                if let Some(waker) = state.waker.take() {
+                    waker.wake();
+                }
+            }
+        }
+        true
+    }
+}
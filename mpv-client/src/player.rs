@@ -0,0 +1,94 @@
+use super::{Event, Format, Handle, Node, Result};
+
+/// The operations of a player used by most plugins: commands, properties,
+/// observers, hooks and events.
+///
+/// `Handle` implements it with libmpv, and `mock::MockPlayer` without, so
+/// plugin logic written against `impl Player` can be unit tested offline.
+pub trait Player {
+    /// See `Handle::command`.
+    fn command<I, S>(&mut self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>;
+
+    /// See `Handle::command_ret`.
+    fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>;
+
+    /// See `Handle::command_node`.
+    fn command_node(&mut self, args: Node) -> Result<Node>;
+
+    /// See `Handle::get_property`.
+    fn get_property<T: Format>(&mut self, name: &str) -> Result<T>;
+
+    /// See `Handle::set_property`.
+    fn set_property<T: Format>(&mut self, name: &str, data: T) -> Result<()>;
+
+    /// See `Handle::observe_property`.
+    fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> Result<()>;
+
+    /// See `Handle::unobserve_property`.
+    fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32>;
+
+    /// See `Handle::hook_add`.
+    fn hook_add(&mut self, reply: u64, name: &str, priority: i32) -> Result<()>;
+
+    /// See `Handle::hook_continue`.
+    fn hook_continue(&mut self, id: u64) -> Result<()>;
+
+    /// See `Handle::wait_event`.
+    fn wait_event(&mut self, timeout: f64) -> Event;
+}
+
+impl Player for Handle {
+    fn command<I, S>(&mut self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Handle::command(self, args)
+    }
+
+    fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Handle::command_ret(self, args)
+    }
+
+    fn command_node(&mut self, args: Node) -> Result<Node> {
+        Handle::command_node(self, args)
+    }
+
+    fn get_property<T: Format>(&mut self, name: &str) -> Result<T> {
+        Handle::get_property(self, name)
+    }
+
+    fn set_property<T: Format>(&mut self, name: &str, data: T) -> Result<()> {
+        Handle::set_property(self, name, data)
+    }
+
+    fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> Result<()> {
+        Handle::observe_property::<T>(self, reply, name)
+    }
+
+    fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32> {
+        Handle::unobserve_property(self, registered_reply)
+    }
+
+    fn hook_add(&mut self, reply: u64, name: &str, priority: i32) -> Result<()> {
+        Handle::hook_add(self, reply, name, priority)
+    }
+
+    fn hook_continue(&mut self, id: u64) -> Result<()> {
+        Handle::hook_continue(self, id)
+    }
+
+    fn wait_event(&mut self, timeout: f64) -> Event {
+        Handle::wait_event(self, timeout)
+    }
+}
@@ -0,0 +1,366 @@
+//! Media information read by a headless player, like `ffprobe`.
+//!
+//! ```ignore
+//! use mpv_client::probe::{probe, ProbePool};
+//!
+//! let info = probe("video.mkv")?;
+//! println!("{:?} {:?}", info.file_format, info.duration);
+//!
+//! // Reuse a few players for many files
+//! let pool = ProbePool::new(4);
+//! for (path, info) in pool.probe_all(paths) { ... }
+//! ```
+
+use super::{mpv_error_MPV_ERROR_GENERIC, mpv_error_MPV_ERROR_LOADING_FAILED, Error};
+use super::{Client, Event, Node, Result};
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default time for a file to be loaded.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The kind of a track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackType {
+    Video,
+    Audio,
+    Sub,
+    Other(String),
+}
+
+/// An entry of the `track-list` property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackInfo {
+    /// The ID used by `vid`, `aid` and `sid`.
+    pub id: i64,
+    pub kind: TrackType,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    /// Whether the track comes from another file, e.g. an external subtitle.
+    pub external: bool,
+    /// Video size, from the demuxer.
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub fps: Option<f64>,
+    /// Audio format, from the demuxer.
+    pub channel_count: Option<i64>,
+    pub sample_rate: Option<i64>,
+}
+
+/// An entry of the `chapter-list` property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChapterInfo {
+    pub title: Option<String>,
+    /// Start of the chapter, in seconds.
+    pub time: f64,
+}
+
+/// The information about a file, see `probe`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MediaInfo {
+    pub path: String,
+    /// The container format, e.g. `mkv` or `mp3`.
+    pub file_format: Option<String>,
+    /// In seconds, `None` for live streams and unknown durations.
+    pub duration: Option<f64>,
+    pub tracks: Vec<TrackInfo>,
+    pub chapters: Vec<ChapterInfo>,
+    /// The tags of the file, e.g. `title` or `artist`.
+    pub metadata: HashMap<String, String>,
+}
+
+/// A headless player loading files only to read their information. It can
+/// probe any number of files, one after another.
+pub struct Prober {
+    client: Client,
+    timeout: Duration,
+}
+
+/// Probers shared by threads, to probe many files.
+pub struct ProbePool {
+    probers: Mutex<Vec<Prober>>,
+    size: usize,
+    timeout: Duration,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn string(map: &HashMap<String, Node>, key: &str) -> Option<String> {
+    match map.get(key) {
+        Some(Node::String(value)) => Some(value.clone()),
+        _ => None,
+    }
+}
+
+fn int(map: &HashMap<String, Node>, key: &str) -> Option<i64> {
+    match map.get(key) {
+        Some(Node::Int(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+fn double(map: &HashMap<String, Node>, key: &str) -> Option<f64> {
+    match map.get(key) {
+        Some(Node::Double(value)) => Some(*value),
+        Some(Node::Int(value)) => Some(*value as f64),
+        _ => None,
+    }
+}
+
+fn flag(map: &HashMap<String, Node>, key: &str) -> bool {
+    matches!(map.get(key), Some(Node::Bool(true)))
+}
+
+fn maps(node: Option<Node>) -> Vec<HashMap<String, Node>> {
+    match node {
+        Some(Node::Array(entries)) => entries
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Node::Map(map) => Some(map),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl TrackType {
+    fn from_name(name: &str) -> Self {
+        match name {
+            "video" => Self::Video,
+            "audio" => Self::Audio,
+            "sub" => Self::Sub,
+            _ => Self::Other(name.to_owned()),
+        }
+    }
+}
+
+impl TrackInfo {
+    fn from_map(map: &HashMap<String, Node>) -> Self {
+        Self {
+            id: int(map, "id").unwrap_or(0),
+            kind: TrackType::from_name(&string(map, "type").unwrap_or_default()),
+            title: string(map, "title"),
+            lang: string(map, "lang"),
+            codec: string(map, "codec"),
+            default: flag(map, "default"),
+            forced: flag(map, "forced"),
+            external: flag(map, "external"),
+            width: int(map, "demux-w"),
+            height: int(map, "demux-h"),
+            fps: double(map, "demux-fps"),
+            channel_count: int(map, "demux-channel-count"),
+            sample_rate: int(map, "demux-samplerate"),
+        }
+    }
+}
+
+impl ChapterInfo {
+    fn from_map(map: &HashMap<String, Node>) -> Self {
+        Self {
+            title: string(map, "title"),
+            time: double(map, "time").unwrap_or(0.),
+        }
+    }
+}
+
+impl MediaInfo {
+    /// The tracks of the given kind.
+    pub fn tracks(&self, kind: TrackType) -> impl Iterator<Item = &TrackInfo> {
+        self.tracks.iter().filter(move |track| track.kind == kind)
+    }
+}
+
+/// Read the information about the file or URL `path`, with a new `Prober`.
+pub fn probe(path: &str) -> Result<MediaInfo> {
+    Prober::new()?.probe(path)
+}
+
+impl Prober {
+    /// Create a player without video and audio outputs, scripts or
+    /// configuration files.
+    pub fn new() -> Result<Self> {
+        let mut client = Client::new()?;
+        let options = [
+            ("config", "no"),
+            ("terminal", "no"),
+            ("idle", "yes"),
+            ("vo", "null"),
+            ("ao", "null"),
+            ("pause", "yes"),
+            ("load-scripts", "no"),
+            ("ytdl", "no"),
+        ];
+        for (name, value) in options {
+            client.set_property(name, value.to_owned())?;
+        }
+        Ok(Self {
+            client: client.initialize()?,
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Give up loading a file after `timeout`, 10 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Load `path`, read its information and unload it.
+    ///
+    /// Fails with the error that ended the loading, e.g.
+    /// `MPV_ERROR_UNKNOWN_FORMAT`, or `MPV_ERROR_LOADING_FAILED` after the
+    /// timeout.
+    pub fn probe(&mut self, path: &str) -> Result<MediaInfo> {
+        let loaded = self.load(path);
+        let info = loaded.map(|()| self.read(path));
+        self.client.command(["stop"])?;
+        info
+    }
+
+    /// Wait for the file of the playlist entry added by `loadfile`. Events
+    /// of the previous files are ignored.
+    fn load(&mut self, path: &str) -> Result<()> {
+        let reply = self.client.command_ret(["loadfile", path, "replace"])?;
+        let entry_id = match reply {
+            Node::Map(map) => int(&map, "playlist_entry_id"),
+            _ => None,
+        };
+        let deadline = Instant::now() + self.timeout;
+        let mut started = false;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::new(mpv_error_MPV_ERROR_LOADING_FAILED));
+            }
+            match self.client.wait_event(remaining.as_secs_f64()) {
+                Event::StartFile(start_file) => {
+                    started = entry_id.is_none() || entry_id == Some(start_file.playlist_entry_id());
+                }
+                Event::FileLoaded if started => return Ok(()),
+                Event::EndFile(end_file) if started => {
+                    return Err(end_file
+                        .error()
+                        .unwrap_or(Error::new(mpv_error_MPV_ERROR_LOADING_FAILED)))
+                }
+                Event::Shutdown => return Err(Error::new(mpv_error_MPV_ERROR_GENERIC)),
+                _ => {}
+            }
+        }
+    }
+
+    fn read(&mut self, path: &str) -> MediaInfo {
+        let client = &mut self.client;
+        let metadata = match client.get_property::<Node>("metadata") {
+            Ok(Node::Map(map)) => map
+                .into_iter()
+                .filter_map(|(key, value)| match value {
+                    Node::String(value) => Some((key, value)),
+                    _ => None,
+                })
+                .collect(),
+            _ => HashMap::new(),
+        };
+        MediaInfo {
+            path: path.to_owned(),
+            file_format: client.get_property::<String>("file-format").ok(),
+            duration: client.get_property::<f64>("duration").ok(),
+            tracks: maps(client.get_property::<Node>("track-list").ok())
+                .iter()
+                .map(TrackInfo::from_map)
+                .collect(),
+            chapters: maps(client.get_property::<Node>("chapter-list").ok())
+                .iter()
+                .map(ChapterInfo::from_map)
+                .collect(),
+            metadata,
+        }
+    }
+}
+
+impl ProbePool {
+    /// Probe with up to `size` players at once. They are created when needed
+    /// and kept for the next files.
+    pub fn new(size: usize) -> Self {
+        Self {
+            probers: Mutex::new(Vec::new()),
+            size: size.max(1),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// See `Prober::timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Take an idle prober, or create one.
+    fn take(&self) -> Result<Prober> {
+        match lock(&self.probers).pop() {
+            Some(prober) => Ok(prober),
+            None => Ok(Prober::new()?.timeout(self.timeout)),
+        }
+    }
+
+    fn give_back(&self, prober: Prober) {
+        let mut probers = lock(&self.probers);
+        if probers.len() < self.size {
+            probers.push(prober);
+        }
+    }
+
+    /// Probe `path` with an idle prober. Can be called from several threads.
+    pub fn probe(&self, path: &str) -> Result<MediaInfo> {
+        let mut prober = self.take()?;
+        let info = prober.probe(path);
+        self.give_back(prober);
+        info
+    }
+
+    /// Probe all the `paths` with `size` threads, and return the results in
+    /// the same order.
+    pub fn probe_all<I, S>(&self, paths: I) -> Vec<(String, Result<MediaInfo>)>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let paths: Vec<String> = paths.into_iter().map(Into::into).collect();
+        let next = Mutex::new(0);
+        let results = Mutex::new(HashMap::new());
+        thread::scope(|scope| {
+            for _ in 0..self.size.min(paths.len()) {
+                scope.spawn(|| loop {
+                    let index = {
+                        let mut next = lock(&next);
+                        *next += 1;
+                        *next - 1
+                    };
+                    let Some(path) = paths.get(index) else {
+                        break;
+                    };
+                    let info = self.probe(path);
+                    lock(&results).insert(index, info);
+                });
+            }
+        });
+
+        let mut results = results.into_inner().unwrap_or_else(|e| e.into_inner());
+        paths
+            .into_iter()
+            .enumerate()
+            .map(|(index, path)| {
+                let info = results.remove(&index).expect("every path was probed");
+                (path, info)
+            })
+            .collect()
+    }
+}
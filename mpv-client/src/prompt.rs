@@ -0,0 +1,329 @@
+//! A line of text input on the OSD, like `mp.input.get` in Lua.
+//!
+//! While the prompt is open, it takes over the keyboard with forced key
+//! bindings. The edit line supports the usual readline keys: `LEFT`/`RIGHT`
+//! (with `Ctrl` to move by word), `HOME`/`END` (`Ctrl+a`/`Ctrl+e`), `BS`,
+//! `DEL`, `Ctrl+w`, `Ctrl+u` and `Ctrl+k` to delete, `UP`/`DOWN` to go
+//! through the history, and `Ctrl+v` or `Shift+INS` to paste from the
+//! clipboard. `ENTER` submits the text, `ESC` cancels.
+
+use super::dispatch;
+use super::input::{self, Binding, InputOverlay};
+use super::osd::{AssBuilder, OsdDimensions};
+use super::{Handle, Result};
+
+use std::sync::{Arc, Mutex};
+
+type OnClose = Box<dyn FnOnce(&mut Handle, Option<String>) + Send>;
+
+/// Entries kept in each history.
+const HISTORY_SIZE: usize = 100;
+
+/// A text prompt, shown with `Handle::open_prompt`.
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    label: String,
+    text: String,
+    history: String,
+}
+
+pub(crate) struct PromptState {
+    prompt: Prompt,
+    text: Vec<char>,
+    /// Position of the cursor in `text`.
+    cursor: usize,
+    history: Vec<String>,
+    /// Position in `history`, `history.len()` when editing a new line.
+    history_pos: usize,
+    /// The new line, saved while browsing the history.
+    draft: Vec<char>,
+    input: Option<InputOverlay>,
+    on_close: Option<OnClose>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    Home,
+    End,
+    Backspace,
+    Delete,
+    DeleteWord,
+    KillStart,
+    KillEnd,
+    HistoryPrev,
+    HistoryNext,
+    Paste,
+    Submit,
+    Cancel,
+    Type,
+}
+
+const BINDINGS: &[Binding<Edit>] = &[
+    ("LEFT", "prompt-left", Edit::Left),
+    ("RIGHT", "prompt-right", Edit::Right),
+    ("Ctrl+LEFT", "prompt-word-left", Edit::WordLeft),
+    ("Ctrl+RIGHT", "prompt-word-right", Edit::WordRight),
+    ("HOME", "prompt-home", Edit::Home),
+    ("Ctrl+a", "prompt-home-ctrl", Edit::Home),
+    ("END", "prompt-end", Edit::End),
+    ("Ctrl+e", "prompt-end-ctrl", Edit::End),
+    ("BS", "prompt-backspace", Edit::Backspace),
+    ("DEL", "prompt-delete", Edit::Delete),
+    ("Ctrl+w", "prompt-delete-word", Edit::DeleteWord),
+    ("Ctrl+BS", "prompt-delete-word-bs", Edit::DeleteWord),
+    ("Ctrl+u", "prompt-kill-start", Edit::KillStart),
+    ("Ctrl+k", "prompt-kill-end", Edit::KillEnd),
+    ("UP", "prompt-history-prev", Edit::HistoryPrev),
+    ("DOWN", "prompt-history-next", Edit::HistoryNext),
+    ("Ctrl+v", "prompt-paste", Edit::Paste),
+    ("Shift+INS", "prompt-paste-ins", Edit::Paste),
+    ("ENTER", "prompt-submit", Edit::Submit),
+    ("KP_ENTER", "prompt-submit-kp", Edit::Submit),
+    ("ESC", "prompt-cancel", Edit::Cancel),
+    ("ANY_UNICODE", "prompt-type", Edit::Type),
+];
+
+impl Prompt {
+    pub fn new(label: &str) -> Self {
+        Self {
+            label: label.to_owned(),
+            text: String::new(),
+            history: String::from("default"),
+        }
+    }
+
+    /// Set the text the prompt opens with.
+    pub fn text(mut self, text: &str) -> Self {
+        self.text = text.to_owned();
+        self
+    }
+
+    /// Set the name of the history used by the prompt, so different prompts
+    /// (e.g. a search and a seek prompt) don't share their history.
+    pub fn history(mut self, name: &str) -> Self {
+        self.history = name.to_owned();
+        self
+    }
+}
+
+fn is_word(c: char) -> bool {
+    !c.is_whitespace() && !c.is_ascii_punctuation()
+}
+
+impl PromptState {
+    fn word_left(&self) -> usize {
+        let mut pos = self.cursor;
+        while pos > 0 && !is_word(self.text[pos - 1]) {
+            pos -= 1;
+        }
+        while pos > 0 && is_word(self.text[pos - 1]) {
+            pos -= 1;
+        }
+        pos
+    }
+
+    fn word_right(&self) -> usize {
+        let mut pos = self.cursor;
+        while pos < self.text.len() && !is_word(self.text[pos]) {
+            pos += 1;
+        }
+        while pos < self.text.len() && is_word(self.text[pos]) {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn insert(&mut self, text: &str) {
+        let chars: Vec<char> = text.chars().map(|c| if c == '\n' { ' ' } else { c }).collect();
+        let len = chars.len();
+        self.text.splice(self.cursor..self.cursor, chars);
+        self.cursor += len;
+    }
+
+    fn browse_history(&mut self, pos: usize) {
+        if pos > self.history.len() || pos == self.history_pos {
+            return;
+        }
+        if self.history_pos == self.history.len() {
+            self.draft = std::mem::take(&mut self.text);
+        }
+        self.history_pos = pos;
+        self.text = match self.history.get(pos) {
+            Some(entry) => entry.chars().collect(),
+            None => std::mem::take(&mut self.draft),
+        };
+        self.cursor = self.text.len();
+    }
+
+    fn apply(&mut self, edit: Edit, text: Option<&str>) {
+        match edit {
+            Edit::Left => self.cursor = self.cursor.saturating_sub(1),
+            Edit::Right => self.cursor = (self.cursor + 1).min(self.text.len()),
+            Edit::WordLeft => self.cursor = self.word_left(),
+            Edit::WordRight => self.cursor = self.word_right(),
+            Edit::Home => self.cursor = 0,
+            Edit::End => self.cursor = self.text.len(),
+            Edit::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.text.remove(self.cursor);
+            }
+            Edit::Delete if self.cursor < self.text.len() => {
+                self.text.remove(self.cursor);
+            }
+            Edit::DeleteWord => {
+                let start = self.word_left();
+                self.text.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            Edit::KillStart => {
+                self.text.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Edit::KillEnd => self.text.truncate(self.cursor),
+            Edit::HistoryPrev => self.browse_history(self.history_pos.saturating_sub(1)),
+            Edit::HistoryNext => self.browse_history(self.history_pos + 1),
+            Edit::Paste | Edit::Type => self.insert(text.unwrap_or_default()),
+            _ => {}
+        }
+    }
+
+    fn render(&self, dimensions: &OsdDimensions) -> String {
+        let font_size = input::font_size(dimensions);
+        let margin = font_size / 2.;
+        let (w, h) = (dimensions.w as f64, dimensions.h as f64);
+        let before: String = self.text[..self.cursor].iter().collect();
+        let after: String = self.text[self.cursor..].iter().collect();
+
+        let mut ass = AssBuilder::new();
+        input::background(&mut ass, 0., h - font_size * 1.25 - 2. * margin, w, h);
+
+        ass.new_event()
+            .an(1)
+            .pos(margin, h - margin)
+            .font_size(font_size)
+            .border(font_size / 16.)
+            .color(0x66ccff)
+            .text(&self.prompt.label)
+            .color(0xffffff)
+            .text(" ")
+            .text(&before)
+            .color(0x66ccff)
+            .append("|")
+            .color(0xffffff)
+            .text(&after);
+        ass.into()
+    }
+}
+
+impl Handle {
+    /// Show `prompt` on the OSD, and call `on_close` with the text when the
+    /// user submits it, or with `None` when the prompt is cancelled. Opening a
+    /// prompt closes the previous one.
+    ///
+    /// The keys are handled by `Handle::wait_event`.
+    pub fn open_prompt<F>(&mut self, prompt: Prompt, on_close: F) -> Result<()>
+    where
+        F: FnOnce(&mut Handle, Option<String>) + Send + 'static,
+    {
+        self.close_prompt_with(None)?;
+
+        let handle = unsafe { self.as_ptr() };
+        let history = dispatch::prompt_history(handle, &prompt.history);
+        let text: Vec<char> = prompt.text.chars().collect();
+        let state = PromptState {
+            cursor: text.len(),
+            text,
+            history_pos: history.len(),
+            history,
+            draft: Vec::new(),
+            prompt,
+            input: None,
+            on_close: Some(Box::new(on_close)),
+        };
+        let state = Arc::new(Mutex::new(state));
+        dispatch::set_prompt(handle, Some(state.clone()));
+
+        let render = state.clone();
+        let input = InputOverlay::open(
+            self,
+            BINDINGS,
+            move |dimensions| render.lock().unwrap_or_else(|e| e.into_inner()).render(dimensions),
+            Handle::prompt_edit,
+        );
+        match input {
+            Ok(input) => {
+                state.lock().unwrap_or_else(|e| e.into_inner()).input = Some(input);
+                Ok(())
+            }
+            Err(e) => {
+                drop(dispatch::set_prompt(handle, None));
+                Err(e)
+            }
+        }
+    }
+
+    /// Close the open prompt, if any. Its `on_close` callback is called with
+    /// `None`.
+    pub fn close_prompt(&mut self) -> Result<()> {
+        self.close_prompt_with(None)
+    }
+
+    fn prompt_edit(&mut self, edit: Edit, text: Option<String>) {
+        let handle = unsafe { self.as_ptr() };
+        let Some(state) = dispatch::prompt(handle) else {
+            return;
+        };
+        let result = match edit {
+            Edit::Submit => {
+                let (history, text) = {
+                    let state = state.lock().unwrap_or_else(|e| e.into_inner());
+                    (state.prompt.history.clone(), state.text.iter().collect::<String>())
+                };
+                if !text.is_empty() {
+                    dispatch::push_prompt_history(handle, &history, text.clone(), HISTORY_SIZE);
+                }
+                self.close_prompt_with(Some(text))
+            }
+            Edit::Cancel => self.close_prompt_with(None),
+            edit => {
+                let text = match edit {
+                    // The clipboard property needs mpv 0.39, paste nothing on
+                    // older versions.
+                    Edit::Paste => self.get_property::<String>("clipboard/text").ok(),
+                    _ => text,
+                };
+                let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                state.apply(edit, text.as_deref());
+                let overlay = state.input.as_ref().map(InputOverlay::id);
+                drop(state);
+                match overlay {
+                    Some(id) => self.render_overlay(id),
+                    None => Ok(()),
+                }
+            }
+        };
+        if let Err(e) = result {
+            self.print_warning(format!("prompt: {}", e));
+        }
+    }
+
+    fn close_prompt_with(&mut self, text: Option<String>) -> Result<()> {
+        let Some(state) = dispatch::set_prompt(unsafe { self.as_ptr() }, None) else {
+            return Ok(());
+        };
+        let (input, on_close) = {
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            (state.input.take(), state.on_close.take())
+        };
+
+        let result = input.map_or(Ok(()), |input| input.close(self));
+        if let Some(on_close) = on_close {
+            on_close(self, text);
+        }
+        result
+    }
+}
@@ -0,0 +1,404 @@
+//! Recording of the event stream to JSON lines, and replay into a plugin.
+//!
+//! `EventRecorder` writes each event received from the `wait_event` loop as
+//! one JSON object per line, e.g.
+//!
+//! ```text
+//! {"t":0.52,"event":"property-change","reply":1,"name":"pause","format":"flag","data":true}
+//! {"t":0.98,"event":"client-message","args":["my-script","toggle"]}
+//! ```
+//!
+//! `Replayer` reads such a file back and implements `Player`: `wait_event`
+//! returns the recorded events in order, while the other calls are stubbed by
+//! a `MockPlayer` whose properties follow the recorded `PropertyChange` events:
+//!
+//! ```ignore
+//! use mpv_client::record::Replayer;
+//!
+//! let mut player = Replayer::open("tests/issue-42.jsonl")?;
+//! my_plugin::run(&mut player);
+//! assert!(player.player().took_command(&["set", "pause", "yes"]));
+//! ```
+//!
+//! Only the events returned by `wait_event` are recorded. The events handled
+//! by the callbacks of `Handle` while waiting, e.g. key bindings, script
+//! messages, property callbacks or command replies, are neither recorded nor
+//! replayed, and the replayed events don't run any callback.
+
+use super::json::{from_json, to_json};
+use super::mock::MockPlayer;
+use super::owned::{OwnedProperty, Value};
+use super::{
+    mpv_format, mpv_format_MPV_FORMAT_DOUBLE, mpv_format_MPV_FORMAT_FLAG, mpv_format_MPV_FORMAT_INT64,
+    mpv_format_MPV_FORMAT_NODE, mpv_format_MPV_FORMAT_NONE, mpv_format_MPV_FORMAT_STRING,
+};
+use super::{
+    ClientMessage, CommandResult, EndFile, EndFileReason, Error, Event, Format, Hook, LogLevel, LogMessage, Node,
+    Player, Property, Result, StartFile,
+};
+
+use serde_json::{json, Map, Value as Json};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// Writes events as JSON lines, see the module documentation.
+pub struct EventRecorder<W: Write> {
+    writer: W,
+    start: Instant,
+}
+
+/// Plays recorded events back, see the module documentation.
+pub struct Replayer {
+    player: MockPlayer,
+    events: VecDeque<Event>,
+}
+
+/// The format name and value of a property event.
+fn property_data(property: &Property) -> (&'static str, Node) {
+    if let Some(node) = property.data::<Node>() {
+        ("node", node)
+    } else if let Some(value) = property.data::<String>() {
+        ("string", Node::String(value))
+    } else if let Some(value) = property.data::<bool>() {
+        ("flag", Node::Bool(value))
+    } else if let Some(value) = property.data::<i64>() {
+        ("int64", Node::Int(value))
+    } else if let Some(value) = property.data::<f64>() {
+        ("double", Node::Double(value))
+    } else {
+        ("none", Node::None)
+    }
+}
+
+fn format_from_name(name: &str) -> Option<mpv_format> {
+    match name {
+        "none" => Some(mpv_format_MPV_FORMAT_NONE),
+        "string" => Some(mpv_format_MPV_FORMAT_STRING),
+        "flag" => Some(mpv_format_MPV_FORMAT_FLAG),
+        "int64" => Some(mpv_format_MPV_FORMAT_INT64),
+        "double" => Some(mpv_format_MPV_FORMAT_DOUBLE),
+        "node" => Some(mpv_format_MPV_FORMAT_NODE),
+        _ => None,
+    }
+}
+
+fn error_code(result: &Result<()>) -> Json {
+    match result {
+        Ok(()) => Json::Null,
+        Err(e) => json!(e.code()),
+    }
+}
+
+fn insert_property(object: &mut Map<String, Json>, property: &Property) {
+    let (format, data) = property_data(property);
+    object.insert(String::from("name"), json!(property.name()));
+    object.insert(String::from("format"), json!(format));
+    object.insert(String::from("data"), to_json(&data));
+}
+
+impl<W: Write> EventRecorder<W> {
+    /// Record to `writer`. The timestamps are relative to this call.
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            start: Instant::now(),
+        }
+    }
+
+    /// Write `event` as a line. `Event::None` is skipped.
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        if let Event::None = event {
+            return Ok(());
+        }
+
+        let mut object = Map::new();
+        object.insert(String::from("t"), json!(self.start.elapsed().as_secs_f64()));
+        object.insert(String::from("event"), json!(event.to_string()));
+        match event {
+            Event::LogMessage(message) => {
+                object.insert(String::from("prefix"), json!(message.prefix()));
+                object.insert(String::from("level"), json!(message.level().as_str()));
+                object.insert(String::from("text"), json!(message.text()));
+            }
+            Event::GetPropertyReply(result, reply, property) => {
+                object.insert(String::from("error"), error_code(result));
+                object.insert(String::from("reply"), json!(reply));
+                insert_property(&mut object, property);
+            }
+            Event::SetPropertyReply(result, reply) => {
+                object.insert(String::from("error"), error_code(result));
+                object.insert(String::from("reply"), json!(reply));
+            }
+            Event::CommandReply(result, reply) => {
+                let node = result.as_ref().map(CommandResult::result).unwrap_or_default();
+                object.insert(
+                    String::from("error"),
+                    error_code(&result.as_ref().map(|_| ()).map_err(|e| *e)),
+                );
+                object.insert(String::from("reply"), json!(reply));
+                object.insert(String::from("result"), to_json(&node));
+            }
+            Event::StartFile(start_file) => {
+                object.insert(String::from("playlist_entry_id"), json!(start_file.playlist_entry_id()));
+            }
+            Event::EndFile(end_file) => {
+                object.insert(String::from("reason"), json!(end_file.reason().to_string()));
+                object.insert(String::from("error"), json!(end_file.error().map(|e| e.code())));
+                object.insert(String::from("playlist_entry_id"), json!(end_file.playlist_entry_id()));
+            }
+            Event::ClientMessage(message) => {
+                object.insert(String::from("args"), json!(message.args()));
+            }
+            Event::PropertyChange(reply, property) => {
+                object.insert(String::from("reply"), json!(reply));
+                insert_property(&mut object, property);
+            }
+            Event::Hook(reply, hook) => {
+                object.insert(String::from("reply"), json!(reply));
+                object.insert(String::from("name"), json!(hook.name()));
+                object.insert(String::from("id"), json!(hook.id()));
+            }
+            _ => {}
+        }
+
+        serde_json::to_writer(&mut self.writer, &object)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+
+    /// Wait for the next event of `player` and record it, to use in place of
+    /// `Handle::wait_event` in the event loop. The events handled by callbacks
+    /// aren't returned, so they aren't recorded.
+    pub fn wait_event<P: Player + ?Sized>(&mut self, player: &mut P, timeout: f64) -> io::Result<Event> {
+        let event = player.wait_event(timeout);
+        self.record(&event)?;
+        Ok(event)
+    }
+
+    /// Consume the recorder, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl EventRecorder<File> {
+    /// Record to the file at `path`, which is truncated.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        File::create(path).map(Self::new)
+    }
+}
+
+/// Accessors for the fields of a recorded event.
+struct Fields<'a>(&'a Map<String, Json>);
+
+impl Fields<'_> {
+    fn get(&self, key: &str) -> io::Result<&Json> {
+        self.0
+            .get(key)
+            .ok_or_else(|| invalid(format!("missing field '{}'", key)))
+    }
+
+    fn str(&self, key: &str) -> io::Result<&str> {
+        self.get(key)?
+            .as_str()
+            .ok_or_else(|| invalid(format!("field '{}' is not a string", key)))
+    }
+
+    fn i64(&self, key: &str) -> io::Result<i64> {
+        self.get(key)?
+            .as_i64()
+            .ok_or_else(|| invalid(format!("field '{}' is not an integer", key)))
+    }
+
+    fn u64(&self, key: &str) -> io::Result<u64> {
+        self.get(key)?
+            .as_u64()
+            .ok_or_else(|| invalid(format!("field '{}' is not an integer", key)))
+    }
+
+    /// An optional error code.
+    fn error(&self) -> io::Result<Option<Error>> {
+        match self.0.get("error") {
+            None | Some(Json::Null) => Ok(None),
+            Some(code) => code
+                .as_i64()
+                .map(|code| Some(Error::new(code as i32)))
+                .ok_or_else(|| invalid(String::from("field 'error' is not an integer"))),
+        }
+    }
+
+    fn result(&self) -> io::Result<Result<()>> {
+        self.error().map(|error| error.map_or(Ok(()), Err))
+    }
+
+    fn property(&self) -> io::Result<Property> {
+        let format = self.str("format")?;
+        let format = format_from_name(format).ok_or_else(|| invalid(format!("unknown format '{}'", format)))?;
+        let value = Value::convert(&from_json(self.get("data")?), format)
+            .ok_or_else(|| invalid(String::from("data doesn't match the format")))?;
+        Ok(Property::from_owned(OwnedProperty::new(self.str("name")?, value)))
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parse a recorded line.
+fn parse_event(line: &str) -> io::Result<Event> {
+    let object: Map<String, Json> = serde_json::from_str(line)?;
+    let fields = Fields(&object);
+    let event = match fields.str("event")? {
+        "shutdown" => Event::Shutdown,
+        "log-message" => {
+            let level = fields.str("level")?;
+            let level = LogLevel::from_name(level).ok_or_else(|| invalid(format!("unknown log level '{}'", level)))?;
+            Event::LogMessage(LogMessage::new(fields.str("prefix")?, level, fields.str("text")?))
+        }
+        "get-property-reply" => Event::GetPropertyReply(fields.result()?, fields.u64("reply")?, fields.property()?),
+        "set-property-reply" => Event::SetPropertyReply(fields.result()?, fields.u64("reply")?),
+        "command-reply" => {
+            let result = from_json(fields.get("result")?);
+            Event::CommandReply(
+                fields.result()?.map(|()| CommandResult::new(&result)),
+                fields.u64("reply")?,
+            )
+        }
+        "start-file" => Event::StartFile(StartFile::new(fields.i64("playlist_entry_id")?)),
+        "end-file" => {
+            let reason = fields.str("reason")?;
+            let reason = EndFileReason::from_name(reason)
+                .ok_or_else(|| invalid(format!("unknown end file reason '{}'", reason)))?;
+            Event::EndFile(EndFile::new(reason, fields.error()?, fields.i64("playlist_entry_id")?))
+        }
+        "file-loaded" => Event::FileLoaded,
+        "client-message" => {
+            let args = fields
+                .get("args")?
+                .as_array()
+                .and_then(|args| args.iter().map(Json::as_str).collect::<Option<Vec<_>>>())
+                .ok_or_else(|| invalid(String::from("field 'args' is not an array of strings")))?;
+            Event::ClientMessage(ClientMessage::new(&args))
+        }
+        "video-reconfig" => Event::VideoReconfig,
+        "audio-reconfig" => Event::AudioReconfig,
+        "seek" => Event::Seek,
+        "playback-restart" => Event::PlaybackRestart,
+        "property-change" => Event::PropertyChange(fields.u64("reply")?, fields.property()?),
+        "event-queue-overflow" => Event::QueueOverflow,
+        "hook" => Event::Hook(fields.u64("reply")?, Hook::new(fields.str("name")?, fields.u64("id")?)),
+        name => return Err(invalid(format!("unknown event '{}'", name))),
+    };
+    Ok(event)
+}
+
+impl Replayer {
+    /// Replay the events recorded in `reader`, with the calls stubbed by
+    /// `player`.
+    pub fn new<R: BufRead>(reader: R, player: MockPlayer) -> io::Result<Self> {
+        let mut events = VecDeque::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event = parse_event(&line).map_err(|e| invalid(format!("line {}: {}", number + 1, e)))?;
+            events.push_back(event);
+        }
+        Ok(Self { player, events })
+    }
+
+    /// Replay the file at `path`, with the calls stubbed by a default
+    /// `MockPlayer`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?), MockPlayer::new())
+    }
+
+    /// The player stubbing the calls, to check the commands run by the plugin.
+    pub fn player(&self) -> &MockPlayer {
+        &self.player
+    }
+
+    pub fn player_mut(&mut self) -> &mut MockPlayer {
+        &mut self.player
+    }
+
+    /// The number of events not replayed yet.
+    pub fn remaining(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Discard the events queued by the mock itself, only the recorded events
+    /// are replayed.
+    fn discard_mock_events(&mut self) {
+        while !matches!(self.player.wait_event(0.0), Event::None) {}
+    }
+}
+
+impl Player for Replayer {
+    fn command<I, S>(&mut self, args: I) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.player.command(args)
+    }
+
+    fn command_ret<I, S>(&mut self, args: I) -> Result<Node>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.player.command_ret(args)
+    }
+
+    fn command_node(&mut self, args: Node) -> Result<Node> {
+        self.player.command_node(args)
+    }
+
+    fn get_property<T: Format>(&mut self, name: &str) -> Result<T> {
+        self.player.get_property(name)
+    }
+
+    fn set_property<T: Format>(&mut self, name: &str, data: T) -> Result<()> {
+        self.player.set_property(name, data)
+    }
+
+    fn observe_property<T: Format>(&mut self, reply: u64, name: &str) -> Result<()> {
+        self.player.observe_property::<T>(reply, name)
+    }
+
+    fn unobserve_property(&mut self, registered_reply: u64) -> Result<i32> {
+        self.player.unobserve_property(registered_reply)
+    }
+
+    fn hook_add(&mut self, reply: u64, name: &str, priority: i32) -> Result<()> {
+        self.player.hook_add(reply, name, priority)
+    }
+
+    fn hook_continue(&mut self, id: u64) -> Result<()> {
+        self.player.hook_continue(id)
+    }
+
+    /// Return the next recorded event, without waiting. A `PropertyChange`
+    /// also updates the property returned by `Player::get_property`. Once all
+    /// the events were replayed, `Event::Shutdown` is returned so the plugin
+    /// leaves its event loop.
+    fn wait_event(&mut self, _timeout: f64) -> Event {
+        self.discard_mock_events();
+        let Some(event) = self.events.pop_front() else {
+            return Event::Shutdown;
+        };
+        if let Event::PropertyChange(_, property) = &event {
+            match property_data(property) {
+                ("none", _) => self.player.unset(property.name()),
+                (_, value) => self.player.set(property.name(), value),
+            }
+            self.discard_mock_events();
+        }
+        event
+    }
+}
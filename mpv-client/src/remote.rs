@@ -0,0 +1,636 @@
+//! Remote control of the player over HTTP and WebSocket, e.g. from a phone on
+//! the local network.
+//!
+//! `RemoteServer` observes a set of properties and serves:
+//!
+//! * `GET /`: a minimal web UI.
+//! * `GET /api/state`: the current value of the observed properties, as a JSON
+//!   object.
+//! * `POST /api/command`: run the command in the body, a JSON array of
+//!   arguments or an object of named arguments, like `Handle::command_node`.
+//! * `PUT /api/property/<name>`: set the property to the JSON value in the
+//!   body.
+//! * `GET /ws`: a WebSocket pushing `{"type": "property", "name": .., "data": ..}`
+//!   on every change, after a first `{"type": "state", "state": {..}}`. It
+//!   accepts `{"id": .., "command": [..]}` and `{"id": .., "set": name, "value": ..}`
+//!   messages, answered with `{"type": "reply", "id": .., "error": .., "data": ..}`.
+//!
+//! Replies carry `"error": "success"` or the mpv error message, like the JSON
+//! IPC protocol. If a token is set, every request must pass it as the `token`
+//! query parameter. A token is required to listen on other addresses than
+//! loopback ones.
+//!
+//! Only the playback commands of `DEFAULT_COMMANDS` are accepted, more can be
+//! allowed with `RemoteServer::command`. Only the properties of
+//! `DEFAULT_PROPERTIES` can be set, with `PUT`, `set` messages or commands
+//! like `set` and `cycle`, more can be allowed with `RemoteServer::settable`.
+//! Requests changing the player from a web page of another origin are
+//! rejected, and so are requests for other hosts than the loopback address
+//! of the server if no token is set.
+//!
+//! ```ignore
+//! use mpv_client::remote::RemoteServer;
+//!
+//! let remote = RemoteServer::new("0.0.0.0:8080")
+//!     .token("secret")
+//!     .property("chapter")
+//!     .start(&mut handle)?;
+//! ```
+
+use super::json::{from_json, to_json};
+use super::{Client, Event, Handle, Node};
+
+use serde_json::{json, Map, Value as Json};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tungstenite::handshake::derive_accept_key;
+use tungstenite::protocol::Role;
+use tungstenite::{Message, WebSocket};
+
+/// The web UI.
+const INDEX: &str = include_str!("remote.html");
+
+/// The properties observed, and settable, by default, used by the web UI.
+pub const DEFAULT_PROPERTIES: [&str; 10] = [
+    "pause",
+    "time-pos",
+    "duration",
+    "volume",
+    "mute",
+    "media-title",
+    "metadata",
+    "playlist",
+    "playlist-pos",
+    "idle-active",
+];
+
+/// The commands accepted by default, enough to control the playback.
+pub const DEFAULT_COMMANDS: [&str; 19] = [
+    "seek",
+    "revert-seek",
+    "frame-step",
+    "frame-back-step",
+    "set",
+    "add",
+    "cycle",
+    "multiply",
+    "cycle-values",
+    "playlist-next",
+    "playlist-prev",
+    "playlist-play-index",
+    "playlist-shuffle",
+    "playlist-unshuffle",
+    "stop",
+    "sub-seek",
+    "sub-step",
+    "ab-loop",
+    "show-text",
+];
+
+/// The commands changing the property given as first argument.
+const PROPERTY_COMMANDS: [&str; 5] = ["set", "add", "cycle", "multiply", "cycle-values"];
+
+/// Longest wait of the player and WebSocket threads before checking for
+/// requests and messages.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Largest accepted request line and headers.
+const MAX_HEAD: u64 = 8192;
+
+/// Largest accepted request body.
+const MAX_BODY: usize = 1 << 20;
+
+/// Longest wait for a request, once connected.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Most connections served at once, WebSockets included. More connections
+/// are closed right away.
+const MAX_CONNECTIONS: usize = 32;
+
+/// An HTTP and WebSocket remote control server, see the module documentation.
+pub struct RemoteServer {
+    address: String,
+    properties: Vec<String>,
+    settable: HashSet<String>,
+    commands: HashSet<String>,
+    token: Option<String>,
+}
+
+/// A running `RemoteServer`. The server is stopped when dropped, the
+/// WebSockets are closed shortly after.
+pub struct RemoteListener {
+    address: SocketAddr,
+    stopped: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// A call to make on the player thread.
+enum Action {
+    Command(Json),
+    SetProperty(String, Json),
+}
+
+type Reply = std::result::Result<Json, String>;
+
+/// The state shared by the threads of a server.
+struct Shared {
+    properties: Mutex<Map<String, Json>>,
+    /// The senders of the messages to push to each WebSocket.
+    sockets: Mutex<Vec<Sender<String>>>,
+    requests: Mutex<Sender<(Action, Sender<Reply>)>>,
+    settable: HashSet<String>,
+    commands: HashSet<String>,
+    token: Option<String>,
+    address: SocketAddr,
+    connections: AtomicUsize,
+    stopped: Arc<AtomicBool>,
+}
+
+/// A parsed HTTP request.
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn reply_message(result: Reply) -> Json {
+    match result {
+        Ok(data) => json!({ "error": "success", "data": data }),
+        Err(error) => json!({ "error": error }),
+    }
+}
+
+/// Decode the `%XX` escapes and `+` of a query string component.
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Compare all the bytes, so the time taken doesn't tell how much of the
+/// token was guessed.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// The name of a command, in the array or object form.
+fn command_name(args: &Json) -> Option<&str> {
+    match args {
+        Json::Array(args) => args.first()?.as_str(),
+        Json::Object(args) => args.get("name")?.as_str(),
+        _ => None,
+    }
+}
+
+/// The property changed by a command of `PROPERTY_COMMANDS`. It is only read
+/// from the array form, the object form names the command and the property
+/// alike.
+fn command_property(args: &Json) -> Option<&str> {
+    let Json::Array(args) = args else {
+        return None;
+    };
+    match args.get(1)?.as_str()? {
+        "!reverse" if args[0] == "cycle-values" => args.get(2)?.as_str(),
+        property => Some(property),
+    }
+}
+
+impl Request {
+    fn read(reader: &mut BufReader<TcpStream>) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_owned());
+
+        let mut head = reader.by_ref().take(MAX_HEAD);
+        let mut line = String::new();
+        head.read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            return Err(invalid("truncated request line"));
+        }
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(invalid("invalid request line"));
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect();
+
+        let mut headers = HashMap::new();
+        loop {
+            let mut line = String::new();
+            if head.read_line(&mut line)? == 0 || !line.ends_with('\n') {
+                return Err(invalid("truncated headers"));
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+            }
+        }
+
+        let length = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+        if length > MAX_BODY {
+            return Err(invalid("request body too large"));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+
+        Ok(Self {
+            method: method.to_owned(),
+            path: percent_decode(path),
+            query,
+            headers,
+            body,
+        })
+    }
+
+    fn header(&self, name: &str) -> &str {
+        self.headers.get(name).map_or("", String::as_str)
+    }
+
+    fn is_websocket(&self) -> bool {
+        self.header("upgrade").eq_ignore_ascii_case("websocket")
+    }
+
+    /// Whether the request doesn't come from a web page of another origin.
+    /// Browsers always send the origin of cross-origin requests, other
+    /// clients usually don't send any.
+    fn is_same_origin(&self) -> bool {
+        match self.header("origin") {
+            "" => true,
+            origin => origin
+                .split_once("://")
+                .is_some_and(|(_, host)| host.eq_ignore_ascii_case(self.header("host"))),
+        }
+    }
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)
+}
+
+fn respond_json(stream: &mut TcpStream, status: &str, body: &Json) -> io::Result<()> {
+    respond(stream, status, "application/json", body.to_string().as_bytes())
+}
+
+impl RemoteServer {
+    /// A server listening on `address`, e.g. `"0.0.0.0:8080"` for the local
+    /// network (with a token) or `"127.0.0.1:0"` for a random local port.
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_owned(),
+            properties: DEFAULT_PROPERTIES.iter().map(|name| name.to_string()).collect(),
+            settable: DEFAULT_PROPERTIES.iter().map(|name| name.to_string()).collect(),
+            commands: DEFAULT_COMMANDS.iter().map(|name| name.to_string()).collect(),
+            token: None,
+        }
+    }
+
+    /// Also observe the property `name`, and publish its value.
+    pub fn property(mut self, name: &str) -> Self {
+        if !self.properties.iter().any(|property| property == name) {
+            self.properties.push(name.to_owned());
+        }
+        self
+    }
+
+    /// Also allow to set the property `name`. Beware of properties running
+    /// programs or writing files, like `input-ipc-server` or `stream-record`.
+    pub fn settable(mut self, name: &str) -> Self {
+        self.settable.insert(name.to_owned());
+        self
+    }
+
+    /// Also accept the command `name`. Beware of commands running programs or
+    /// loading files, like `run`, `subprocess` or `load-script`.
+    pub fn command(mut self, name: &str) -> Self {
+        self.commands.insert(name.to_owned());
+        self
+    }
+
+    /// Require every request to pass `token` as the `token` query parameter.
+    pub fn token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_owned());
+        self
+    }
+
+    /// Start serving in background threads. The properties are observed, and
+    /// the commands run, by a client created from `handle`.
+    ///
+    /// Fails with `PermissionDenied` if the address is not a loopback one and
+    /// no token is set.
+    pub fn start(self, handle: &mut Handle) -> io::Result<RemoteListener> {
+        let listener = TcpListener::bind(&self.address)?;
+        let address = listener.local_addr()?;
+        if self.token.is_none() && !address.ip().is_loopback() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "a token is required to listen on a non-loopback address",
+            ));
+        }
+
+        let mut client = handle
+            .create_client("remote")
+            .map_err(|e| io::Error::other(e.to_string()))?;
+        for (reply, name) in self.properties.iter().enumerate() {
+            client
+                .observe_property::<Node>(reply as u64, name)
+                .map_err(|e| io::Error::other(e.to_string()))?;
+        }
+
+        let stopped = Arc::new(AtomicBool::new(false));
+        let (sender, receiver) = mpsc::channel();
+        let shared = Arc::new(Shared {
+            properties: Mutex::new(Map::new()),
+            sockets: Mutex::new(Vec::new()),
+            requests: Mutex::new(sender),
+            settable: self.settable,
+            commands: self.commands,
+            token: self.token,
+            address,
+            connections: AtomicUsize::new(0),
+            stopped: stopped.clone(),
+        });
+
+        let player = {
+            let shared = shared.clone();
+            thread::spawn(move || run_player(client, receiver, &shared))
+        };
+        let server = thread::spawn(move || {
+            for stream in listener.incoming() {
+                if shared.stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                if shared.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    shared.connections.fetch_sub(1, Ordering::SeqCst);
+                    continue;
+                }
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    let _ = serve(stream, &shared);
+                    shared.connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+
+        Ok(RemoteListener {
+            address,
+            stopped,
+            threads: vec![server, player],
+        })
+    }
+}
+
+impl RemoteListener {
+    /// The address the server listens on.
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+impl Drop for RemoteListener {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Unblock the accept loop.
+        let _ = TcpStream::connect(self.address);
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Run the requests of the connections, and publish the property changes,
+/// until the server is stopped or the player shuts down.
+fn run_player(mut client: Client, requests: Receiver<(Action, Sender<Reply>)>, shared: &Shared) {
+    while !shared.stopped.load(Ordering::SeqCst) {
+        while let Ok((action, reply)) = requests.try_recv() {
+            let result = match action {
+                Action::Command(args) => client.command_node(from_json(&args)).map(|node| to_json(&node)),
+                Action::SetProperty(name, value) => client.set_property(&name, from_json(&value)).map(|()| Json::Null),
+            };
+            let _ = reply.send(result.map_err(|e| e.message().to_owned()));
+        }
+
+        match client.wait_event(POLL_INTERVAL.as_secs_f64()) {
+            Event::PropertyChange(_, property) => {
+                let data = property.data::<Node>().map_or(Json::Null, |node| to_json(&node));
+                let message = json!({ "type": "property", "name": property.name(), "data": data }).to_string();
+                lock(&shared.properties).insert(property.name().to_owned(), data);
+                lock(&shared.sockets).retain(|socket| socket.send(message.clone()).is_ok());
+            }
+            Event::Shutdown => break,
+            _ => {}
+        }
+    }
+    shared.stopped.store(true, Ordering::SeqCst);
+}
+
+impl Shared {
+    /// Run `action` on the player thread and wait for the result.
+    fn call(&self, action: Action) -> Reply {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.requests)
+            .send((action, sender))
+            .map_err(|_| String::from("the player shut down"))?;
+        receiver.recv().map_err(|_| String::from("the player shut down"))?
+    }
+
+    /// Run the command `args` if it is allowed, and so is the property it
+    /// changes.
+    fn command(&self, args: Json) -> Reply {
+        match command_name(&args) {
+            Some(name) if !self.commands.contains(name) => Err(format!("command '{}' not allowed", name)),
+            Some(name) if PROPERTY_COMMANDS.contains(&name) => match command_property(&args) {
+                Some(property) if self.settable.contains(property) => self.call(Action::Command(args)),
+                Some(property) => Err(format!("property '{}' not settable", property)),
+                None => Err(String::from("invalid command")),
+            },
+            Some(_) => self.call(Action::Command(args)),
+            None => Err(String::from("invalid command")),
+        }
+    }
+
+    /// Set the property `name` if it is allowed.
+    fn set_property(&self, name: String, value: Json) -> Reply {
+        if !self.settable.contains(&name) {
+            return Err(format!("property '{}' not settable", name));
+        }
+        self.call(Action::SetProperty(name, value))
+    }
+
+    /// Whether `host` names the server on the loopback interface. Pages of
+    /// other domains rebound to the loopback address send their own domain.
+    fn is_local_host(&self, host: &str) -> bool {
+        let port = self.address.port();
+        let local = [
+            self.address.to_string(),
+            format!("localhost:{}", port),
+            format!("127.0.0.1:{}", port),
+            format!("[::1]:{}", port),
+        ];
+        local.iter().any(|local| local.eq_ignore_ascii_case(host))
+    }
+
+    fn state(&self) -> Json {
+        Json::Object(lock(&self.properties).clone())
+    }
+}
+
+/// Serve the request of a connection.
+fn serve(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut stream = stream;
+    let request = match Request::read(&mut reader) {
+        Ok(request) => request,
+        Err(e) => return respond(&mut stream, "400 Bad Request", "text/plain", e.to_string().as_bytes()),
+    };
+
+    match &shared.token {
+        Some(token) => {
+            let given = request.query.get("token").map_or("", String::as_str);
+            if !constant_time_eq(given.as_bytes(), token.as_bytes()) {
+                return respond(&mut stream, "401 Unauthorized", "text/plain", b"invalid token");
+            }
+        }
+        None if !shared.is_local_host(request.header("host")) => {
+            return respond(&mut stream, "403 Forbidden", "text/plain", b"unknown host");
+        }
+        None => {}
+    }
+    if (request.method != "GET" || request.is_websocket()) && !request.is_same_origin() {
+        return respond(&mut stream, "403 Forbidden", "text/plain", b"cross-origin request");
+    }
+
+    let parse_body = || serde_json::from_slice::<Json>(&request.body).map_err(|e| e.to_string());
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") | ("GET", "/index.html") => {
+            respond(&mut stream, "200 OK", "text/html; charset=utf-8", INDEX.as_bytes())
+        }
+        ("GET", "/api/state") => respond_json(&mut stream, "200 OK", &shared.state()),
+        ("GET", "/ws") if request.is_websocket() => serve_websocket(stream, &request, shared),
+        ("POST", "/api/command") => {
+            let result = parse_body().and_then(|args| shared.command(args));
+            respond_json(&mut stream, "200 OK", &reply_message(result))
+        }
+        ("PUT", path) if path.starts_with("/api/property/") => {
+            let name = path["/api/property/".len()..].to_owned();
+            let result = parse_body().and_then(|value| shared.set_property(name, value));
+            respond_json(&mut stream, "200 OK", &reply_message(result))
+        }
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found"),
+    }
+}
+
+/// Complete the WebSocket handshake, then push the property changes and run
+/// the received messages until the socket is closed.
+fn serve_websocket(mut stream: TcpStream, request: &Request, shared: &Shared) -> io::Result<()> {
+    let accept = derive_accept_key(request.header("sec-websocket-key").as_bytes());
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept
+    )?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let (sender, receiver) = mpsc::channel();
+    lock(&shared.sockets).push(sender);
+    let mut socket = WebSocket::from_raw_socket(stream, Role::Server, None);
+    let state = json!({ "type": "state", "state": shared.state() });
+    if socket.send(Message::Text(state.to_string())).is_err() {
+        return Ok(());
+    }
+
+    while !shared.stopped.load(Ordering::SeqCst) {
+        while let Ok(message) = receiver.try_recv() {
+            if socket.send(Message::Text(message)).is_err() {
+                return Ok(());
+            }
+        }
+
+        match socket.read() {
+            Ok(Message::Text(text)) => {
+                let reply = handle_message(&text, shared);
+                if socket.send(Message::Text(reply.to_string())).is_err() {
+                    return Ok(());
+                }
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {}
+            Err(_) => break,
+        }
+    }
+    Ok(())
+}
+
+/// Run a message received on a WebSocket, and return the reply.
+fn handle_message(text: &str, shared: &Shared) -> Json {
+    let message: Map<String, Json> = match serde_json::from_str(text) {
+        Ok(message) => message,
+        Err(e) => return json!({ "type": "reply", "id": null, "error": e.to_string() }),
+    };
+    let result = if let Some(args) = message.get("command") {
+        shared.command(args.clone())
+    } else if let Some(name) = message.get("set").and_then(Json::as_str) {
+        let value = message.get("value").cloned().unwrap_or(Json::Null);
+        shared.set_property(name.to_owned(), value)
+    } else {
+        Err(String::from("expected 'command' or 'set'"))
+    };
+
+    let mut reply = reply_message(result);
+    reply["type"] = json!("reply");
+    reply["id"] = message.get("id").cloned().unwrap_or(Json::Null);
+    reply
+}
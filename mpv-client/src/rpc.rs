@@ -0,0 +1,168 @@
+//! Request/response calls between clients, over `script-message-to`.
+//!
+//! A request is sent to the target client as the script message
+//!
+//! ```text
+//! rpc-request <id> <reply address> <method> <JSON params>
+//! ```
+//!
+//! and answered by sending to the reply address (`@<client id>`) either
+//!
+//! ```text
+//! rpc-reply <id> ok <JSON result>
+//! rpc-reply <id> error <message>
+//! ```
+//!
+//! so Lua or JavaScript scripts can take part with `mp.register_script_message`
+//! and `utils.parse_json`/`utils.format_json`.
+
+use super::dispatch;
+use super::{mpv_error_MPV_ERROR_INVALID_PARAMETER, Error, Handle, Result, Timer};
+
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub(crate) type Method = Arc<Mutex<dyn FnMut(&mut Handle, Value) -> std::result::Result<Value, String> + Send>>;
+
+type Callback = Box<dyn FnOnce(&mut Handle, std::result::Result<Value, RpcError>) + Send>;
+
+/// A call waiting for its reply.
+pub(crate) struct Pending {
+    callback: Callback,
+    _timeout: Timer,
+}
+
+/// Error of a call made with `Handle::call_rpc`.
+#[derive(Debug)]
+pub enum RpcError {
+    /// No reply was received before the timeout.
+    Timeout,
+    /// The method failed, or doesn't exist, in the target client.
+    Remote(String),
+    /// The reply couldn't be decoded.
+    InvalidReply(String),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("timeout"),
+            Self::Remote(message) => write!(f, "remote error: {}", message),
+            Self::InvalidReply(message) => write!(f, "invalid reply: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+impl Handle {
+    /// Expose `method` to the other clients. It receives the decoded JSON
+    /// params, and its result or error is sent back to the caller.
+    pub fn register_rpc_method<F>(&mut self, name: &str, method: F)
+    where
+        F: FnMut(&mut Handle, Value) -> std::result::Result<Value, String> + Send + 'static,
+    {
+        let handle = unsafe { self.as_ptr() };
+        dispatch::add_rpc_method(handle, name.to_owned(), Arc::new(Mutex::new(method)));
+        if dispatch::add_rpc_message(handle, "rpc-request") {
+            self.register_script_message(
+                "rpc-request",
+                |handle, (id, reply_to, method, params): (u64, String, String, String)| {
+                    handle.serve_rpc(id, &reply_to, &method, &params)
+                },
+            );
+        }
+    }
+
+    /// Call `method` in the client `target` (a client name, or `@<id>`) with the
+    /// given params. `callback` is run by `Handle::wait_event` with the result,
+    /// or with `RpcError::Timeout` if no reply came within `timeout`.
+    pub fn call_rpc<F>(
+        &mut self,
+        target: &str,
+        method: &str,
+        params: &Value,
+        timeout: Duration,
+        callback: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut Handle, std::result::Result<Value, RpcError>) + Send + 'static,
+    {
+        let handle = unsafe { self.as_ptr() };
+        let id = dispatch::next_rpc_id(handle);
+        let params = serde_json::to_string(params).map_err(|_| Error::new(mpv_error_MPV_ERROR_INVALID_PARAMETER))?;
+
+        if dispatch::add_rpc_message(handle, "rpc-reply") {
+            self.register_script_message("rpc-reply", |handle, (id, status, payload): (u64, String, String)| {
+                handle.receive_rpc_reply(id, &status, &payload)
+            });
+        }
+
+        let timeout = self.add_timeout(timeout, move |handle| {
+            if let Some(pending) = dispatch::take_rpc_pending(unsafe { handle.as_ptr() }, id) {
+                (pending.callback)(handle, Err(RpcError::Timeout));
+            }
+        });
+        let pending = Pending {
+            callback: Box::new(callback),
+            _timeout: timeout,
+        };
+        dispatch::add_rpc_pending(handle, id, pending);
+
+        let reply_to = format!("@{}", self.id());
+        let sent = self.command([
+            "script-message-to",
+            target,
+            "rpc-request",
+            &id.to_string(),
+            &reply_to,
+            method,
+            &params,
+        ]);
+        if sent.is_err() {
+            drop(dispatch::take_rpc_pending(handle, id));
+        }
+        sent
+    }
+
+    fn serve_rpc(&mut self, id: u64, reply_to: &str, method: &str, params: &str) {
+        let result = match dispatch::rpc_method(unsafe { self.as_ptr() }, method) {
+            None => Err(format!("unknown method '{}'", method)),
+            Some(method) => match serde_json::from_str(params) {
+                Ok(params) => method.lock().unwrap_or_else(|e| e.into_inner())(self, params),
+                Err(e) => Err(format!("invalid params: {}", e)),
+            },
+        };
+
+        let (status, payload) = match result.map(|value| value.to_string()) {
+            Ok(value) => ("ok", value),
+            Err(message) => ("error", message),
+        };
+        if let Err(e) = self.command([
+            "script-message-to",
+            reply_to,
+            "rpc-reply",
+            &id.to_string(),
+            status,
+            &payload,
+        ]) {
+            self.print_warning(format!("failed to reply to rpc call {} from {}: {}", id, reply_to, e));
+        }
+    }
+
+    fn receive_rpc_reply(&mut self, id: u64, status: &str, payload: &str) {
+        let Some(pending) = dispatch::take_rpc_pending(unsafe { self.as_ptr() }, id) else {
+            // The call already timed out.
+            return;
+        };
+
+        let result = match status {
+            "ok" => serde_json::from_str(payload).map_err(|e| RpcError::InvalidReply(e.to_string())),
+            "error" => Err(RpcError::Remote(payload.to_owned())),
+            status => Err(RpcError::InvalidReply(format!("unknown status '{}'", status))),
+        };
+        (pending.callback)(self, result);
+    }
+}
@@ -0,0 +1,178 @@
+//! Screenshots as images in memory, with the `screenshot-raw` command.
+//!
+//! ```ignore
+//! use mpv_client::commands::ScreenshotMode;
+//! use mpv_client::screenshot::extract_frames;
+//!
+//! let image = handle.screenshot_raw(ScreenshotMode::Video)?;
+//! let thumbnails = extract_frames(handle, &[10., 60., 120.], ScreenshotMode::Video)?;
+//! ```
+//!
+//! With the `png` feature, images can be encoded with `Image::to_png`.
+
+use super::commands::ScreenshotMode;
+use super::{mpv_error_MPV_ERROR_GENERIC, mpv_error_MPV_ERROR_UNSUPPORTED, Error};
+use super::{Event, Handle, Node, Result};
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Longest wait for a seek of `extract_frames`.
+const SEEK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A screenshot, see `Handle::screenshot_raw`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Bytes between the starts of two rows, at least `width` times the
+    /// pixel size.
+    pub stride: usize,
+    /// The pixel format: `bgr0` (the default of mpv), `bgra`, `rgba` or
+    /// `rgba64`.
+    pub format: String,
+    /// `height` rows of `stride` bytes.
+    pub data: Vec<u8>,
+}
+
+impl Image {
+    fn from_node(node: Node) -> Option<Self> {
+        let Node::Map(mut map) = node else {
+            return None;
+        };
+        let size = |map: &HashMap<String, Node>, key: &str| match map.get(key) {
+            Some(Node::Int(value)) => usize::try_from(*value).ok(),
+            _ => None,
+        };
+        let image = Self {
+            width: size(&map, "w")?,
+            height: size(&map, "h")?,
+            stride: size(&map, "stride")?,
+            format: match map.remove("format") {
+                Some(Node::String(format)) => format,
+                _ => return None,
+            },
+            data: match map.remove("data") {
+                Some(Node::ByteArray(data)) => data,
+                _ => return None,
+            },
+        };
+        (image.data.len() >= image.stride * image.height).then_some(image)
+    }
+
+    /// The row `y` of pixels, without the padding.
+    pub fn row(&self, y: usize) -> &[u8] {
+        let start = y * self.stride;
+        &self.data[start..start + self.width * self.pixel_size()]
+    }
+
+    /// The size of a pixel in bytes, 0 for an unknown format.
+    pub fn pixel_size(&self) -> usize {
+        match self.format.as_str() {
+            "bgr0" | "bgra" | "rgb0" | "rgba" => 4,
+            "rgba64" => 8,
+            _ => 0,
+        }
+    }
+
+    /// The pixels as packed 8 bit RGBA, without padding. `None` for an
+    /// unknown format.
+    pub fn to_rgba(&self) -> Option<Vec<u8>> {
+        let convert: fn(&[u8]) -> [u8; 4] = match self.format.as_str() {
+            "bgr0" => |p| [p[2], p[1], p[0], 255],
+            "bgra" => |p| [p[2], p[1], p[0], p[3]],
+            "rgb0" => |p| [p[0], p[1], p[2], 255],
+            "rgba" => |p| [p[0], p[1], p[2], p[3]],
+            // 16 bit little-endian components, keep the most significant byte
+            "rgba64" => |p| [p[1], p[3], p[5], p[7]],
+            _ => return None,
+        };
+        let mut rgba = Vec::with_capacity(self.width * self.height * 4);
+        for y in 0..self.height {
+            for pixel in self.row(y).chunks_exact(self.pixel_size()) {
+                rgba.extend_from_slice(&convert(pixel));
+            }
+        }
+        Some(rgba)
+    }
+
+    /// Encode the image as PNG, in 8 bit RGBA.
+    #[cfg(feature = "png")]
+    pub fn write_png<W: std::io::Write>(&self, writer: W) -> std::io::Result<()> {
+        use std::io;
+
+        let rgba = self
+            .to_rgba()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("pixel format {}", self.format)))?;
+        let width = u32::try_from(self.width).map_err(io::Error::other)?;
+        let height = u32::try_from(self.height).map_err(io::Error::other)?;
+        let mut encoder = png::Encoder::new(writer, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&rgba).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    /// The image encoded as PNG, see `Image::write_png`.
+    #[cfg(feature = "png")]
+    pub fn to_png(&self) -> std::io::Result<Vec<u8>> {
+        let mut png = Vec::new();
+        self.write_png(&mut png)?;
+        Ok(png)
+    }
+}
+
+impl Handle {
+    /// Take a screenshot of the current frame, and return it instead of
+    /// saving it. The video output doesn't need to support screenshots, so
+    /// this works with `vo=null`.
+    pub fn screenshot_raw(&mut self, mode: ScreenshotMode) -> Result<Image> {
+        let args = HashMap::from([
+            ("name".to_owned(), Node::String("screenshot-raw".to_owned())),
+            ("flags".to_owned(), Node::String(mode.as_str().to_owned())),
+        ]);
+        let node = self.command_node(Node::Map(args))?;
+        Image::from_node(node).ok_or(Error::new(mpv_error_MPV_ERROR_UNSUPPORTED))
+    }
+}
+
+/// Seek to each of the `timestamps` (in seconds) of the current file, and take
+/// a screenshot. The player is paused while seeking, and resumed afterwards
+/// if it was playing.
+///
+/// The seeks are waited for with a new client, so the events of `handle` are
+/// not consumed. Fails with `MPV_ERROR_GENERIC` if a seek doesn't finish
+/// within 10 seconds.
+pub fn extract_frames(handle: &mut Handle, timestamps: &[f64], mode: ScreenshotMode) -> Result<Vec<Image>> {
+    let mut client = handle.create_client("frames")?;
+    let paused = client.get_property::<bool>("pause")?;
+    client.set_property("pause", true)?;
+
+    let mut frames = Vec::with_capacity(timestamps.len());
+    let mut extract = || {
+        for timestamp in timestamps {
+            client.command(["seek", &timestamp.to_string(), "absolute+exact"])?;
+            let deadline = Instant::now() + SEEK_TIMEOUT;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    return Err(Error::new(mpv_error_MPV_ERROR_GENERIC));
+                }
+                match client.wait_event(remaining.as_secs_f64()) {
+                    Event::PlaybackRestart => break,
+                    Event::Shutdown => return Err(Error::new(mpv_error_MPV_ERROR_GENERIC)),
+                    _ => {}
+                }
+            }
+            frames.push(client.screenshot_raw(mode)?);
+        }
+        Ok(())
+    };
+    let extracted = extract();
+
+    if !paused {
+        client.set_property("pause", false)?;
+    }
+    extracted.map(|()| frames)
+}
@@ -0,0 +1,237 @@
+//! External programs run by mpv with the `subprocess` command, like
+//! `mp.command_native({name = "subprocess", ...})` in Lua.
+//!
+//! ```ignore
+//! use mpv_client::subprocess::Subprocess;
+//!
+//! let output = Subprocess::new(["yt-dlp", "--get-title", url])
+//!     .capture_stdout(true)
+//!     .run(handle)?;
+//! ```
+
+use super::{CommandResult, Handle, Node, PendingCommand, Result};
+
+use std::collections::HashMap;
+
+/// A program to run with the `subprocess` command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Subprocess {
+    args: Vec<String>,
+    playback_only: bool,
+    capture_size: Option<i64>,
+    capture_stdout: bool,
+    capture_stderr: bool,
+    detach: bool,
+    env: Option<Vec<String>>,
+    stdin: Option<String>,
+    passthrough_stdin: bool,
+}
+
+/// Why a subprocess didn't run to completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KillReason {
+    /// The process couldn't be started.
+    Init,
+    /// The process was killed, by `Handle::abort_async_command`, by the end of
+    /// playback (see `Subprocess::playback_only`) or by the player shutting
+    /// down.
+    Killed,
+    /// Another error, with mpv's description.
+    Other(String),
+}
+
+/// The result of a subprocess.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SubprocessOutput {
+    /// The exit code of the process, or a negative value if it didn't exit
+    /// normally.
+    pub status: i64,
+    /// The captured standard output, up to the capture size.
+    pub stdout: Vec<u8>,
+    /// The captured standard error, up to the capture size.
+    pub stderr: Vec<u8>,
+    /// Why the process didn't run to completion, `None` if it exited.
+    pub kill_reason: Option<KillReason>,
+    /// Whether the process was killed by mpv, rather than by a signal from
+    /// elsewhere.
+    pub killed_by_us: bool,
+}
+
+impl Subprocess {
+    /// Run the program `args[0]` with the arguments `args[1..]`. The program is
+    /// searched in `PATH`.
+    pub fn new<I, S>(args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            args: args.into_iter().map(Into::into).collect(),
+            playback_only: true,
+            capture_size: None,
+            capture_stdout: false,
+            capture_stderr: false,
+            detach: false,
+            env: None,
+            stdin: None,
+            passthrough_stdin: false,
+        }
+    }
+
+    /// Kill the process when playback of the current file ends (default
+    /// `true`), otherwise only when the player quits.
+    pub fn playback_only(mut self, playback_only: bool) -> Self {
+        self.playback_only = playback_only;
+        self
+    }
+
+    /// Capture the standard output of the process.
+    pub fn capture_stdout(mut self, capture: bool) -> Self {
+        self.capture_stdout = capture;
+        self
+    }
+
+    /// Capture the standard error of the process.
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
+    }
+
+    /// Set the maximum number of bytes captured from each output (64 MB by
+    /// default).
+    pub fn capture_size(mut self, bytes: usize) -> Self {
+        self.capture_size = Some(bytes as i64);
+        self
+    }
+
+    /// Detach the process, so mpv doesn't wait for it or kill it. Nothing is
+    /// captured and the status isn't known.
+    pub fn detach(mut self, detach: bool) -> Self {
+        self.detach = detach;
+        self
+    }
+
+    /// Run the process with these `NAME=value` environment variables, instead
+    /// of mpv's environment.
+    pub fn env<I, S>(mut self, env: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.env = Some(env.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Write `data` to the standard input of the process.
+    pub fn stdin(mut self, data: &str) -> Self {
+        self.stdin = Some(data.to_owned());
+        self
+    }
+
+    /// Let the process read mpv's standard input.
+    pub fn passthrough_stdin(mut self, passthrough: bool) -> Self {
+        self.passthrough_stdin = passthrough;
+        self
+    }
+
+    /// The `subprocess` command, for `Handle::command_node`.
+    pub fn node(&self) -> Node {
+        let mut args = HashMap::new();
+        args.insert("name".to_owned(), Node::String("subprocess".to_owned()));
+        args.insert(
+            "args".to_owned(),
+            Node::Array(self.args.iter().cloned().map(Node::String).collect()),
+        );
+        args.insert("playback_only".to_owned(), Node::Bool(self.playback_only));
+        args.insert("capture_stdout".to_owned(), Node::Bool(self.capture_stdout));
+        args.insert("capture_stderr".to_owned(), Node::Bool(self.capture_stderr));
+        args.insert("detach".to_owned(), Node::Bool(self.detach));
+        args.insert("passthrough_stdin".to_owned(), Node::Bool(self.passthrough_stdin));
+        if let Some(size) = self.capture_size {
+            args.insert("capture_size".to_owned(), Node::Int(size));
+        }
+        if let Some(env) = &self.env {
+            args.insert(
+                "env".to_owned(),
+                Node::Array(env.iter().cloned().map(Node::String).collect()),
+            );
+        }
+        if let Some(stdin) = &self.stdin {
+            args.insert("stdin_data".to_owned(), Node::String(stdin.clone()));
+        }
+        Node::Map(args)
+    }
+
+    /// Run the process and wait for it to exit.
+    ///
+    /// This blocks the client, prefer `Subprocess::spawn` for long-running
+    /// programs.
+    pub fn run(&self, handle: &mut Handle) -> Result<SubprocessOutput> {
+        handle.command_node(self.node()).map(SubprocessOutput::from_node)
+    }
+
+    /// Run the process asynchronously. The token resolves with the result of
+    /// the command (see `SubprocessOutput::from_node`), and
+    /// `PendingCommand::abort` kills the process.
+    pub fn spawn(&self, handle: &mut Handle) -> Result<PendingCommand> {
+        handle.command_node_async_pending(self.node())
+    }
+}
+
+fn bytes(node: Option<Node>) -> Vec<u8> {
+    match node {
+        Some(Node::ByteArray(bytes)) => bytes,
+        Some(Node::String(string)) => string.into_bytes(),
+        _ => Vec::new(),
+    }
+}
+
+impl SubprocessOutput {
+    /// Read the result of the `subprocess` command. Anything else than a map
+    /// is read as a process that didn't run.
+    pub fn from_node(node: Node) -> Self {
+        let Node::Map(mut map) = node else {
+            return Self {
+                status: -1,
+                kill_reason: Some(KillReason::Other("no subprocess result".to_owned())),
+                ..Self::default()
+            };
+        };
+        let status = match map.remove("status") {
+            Some(Node::Int(status)) => status,
+            _ => -1,
+        };
+        let kill_reason = match map.remove("error_string") {
+            Some(Node::String(error)) => match error.as_str() {
+                "" => None,
+                "init" => Some(KillReason::Init),
+                "killed" => Some(KillReason::Killed),
+                _ => Some(KillReason::Other(error)),
+            },
+            _ => None,
+        };
+        Self {
+            status,
+            stdout: bytes(map.remove("stdout")),
+            stderr: bytes(map.remove("stderr")),
+            kill_reason,
+            killed_by_us: matches!(map.remove("killed_by_us"), Some(Node::Bool(true))),
+        }
+    }
+
+    /// Read the result of a process started with `Handle::command_node_async`
+    /// and `Subprocess::node`.
+    pub fn from_reply(command: &CommandResult) -> Self {
+        Self::from_node(command.result())
+    }
+
+    /// Whether the process exited with status 0.
+    pub fn success(&self) -> bool {
+        self.status == 0 && self.kill_reason.is_none()
+    }
+
+    /// The captured standard output as text, with invalid UTF-8 replaced.
+    pub fn stdout_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+}
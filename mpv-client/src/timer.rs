@@ -0,0 +1,155 @@
+use super::dispatch::{self, HandleRef};
+use super::{mpv_get_time_us, mpv_wakeup, Handle};
+
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+type Callback = Box<dyn FnMut(&mut Handle) + Send>;
+
+/// The shortest period of a periodic timer, shorter ones would keep the event
+/// loop busy.
+const MIN_PERIOD: Duration = Duration::from_millis(1);
+
+/// A timer registered with `Handle::add_timeout` or `Handle::add_periodic_timer`.
+///
+/// The callback is run by `Handle::wait_event`, on the thread waiting for
+/// events. Dropping the timer cancels it. The timer can outlive the client,
+/// it then does nothing.
+pub struct Timer {
+    entry: Arc<TimerEntry>,
+    handle: HandleRef,
+}
+
+pub(crate) struct TimerEntry {
+    interval: i64,
+    periodic: bool,
+    /// Next expiration in mpv_get_time_us() time, or `None` when killed.
+    next: Mutex<Option<i64>>,
+    callback: Mutex<Callback>,
+}
+
+impl Timer {
+    pub(crate) fn new(handle: &mut Handle, timeout: Duration, periodic: bool, callback: Callback) -> Self {
+        let interval = timeout.as_micros().min(i64::MAX as u128) as i64;
+        let entry = Arc::new(TimerEntry {
+            interval,
+            periodic,
+            next: Mutex::new(Some(handle.get_time_us().saturating_add(interval))),
+            callback: Mutex::new(callback),
+        });
+        dispatch::add_timer(unsafe { handle.as_ptr() }, entry.clone());
+        // The thread waiting for events must take the new timer into account.
+        unsafe { mpv_wakeup(handle.as_mut_ptr()) };
+        Self {
+            entry,
+            handle: HandleRef::new(handle),
+        }
+    }
+
+    /// Stop the timer. The callback won't be called until `Timer::resume`.
+    pub fn kill(&self) {
+        *self.entry.next() = None;
+        self.handle.with(|handle| unsafe { mpv_wakeup(handle.as_mut_ptr()) });
+    }
+
+    /// Restart the timer, the callback is called after the full timeout again.
+    pub fn resume(&self) {
+        self.handle.with(|handle| {
+            let now = unsafe { mpv_get_time_us(handle.as_mut_ptr()) };
+            *self.entry.next() = Some(now.saturating_add(self.entry.interval));
+            unsafe { mpv_wakeup(handle.as_mut_ptr()) };
+        });
+    }
+
+    /// Whether the timer is running, i.e. was not killed and, if it is a
+    /// one-shot timer, has not expired yet.
+    pub fn is_enabled(&self) -> bool {
+        self.entry.next().is_some()
+    }
+
+    /// The timeout, or the period of a periodic timer.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_micros(self.entry.interval as u64)
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        *self.entry.next() = None;
+        self.handle
+            .with(|handle| dispatch::remove_timer(unsafe { handle.as_ptr() }, &self.entry));
+    }
+}
+
+impl TimerEntry {
+    fn next(&self) -> MutexGuard<'_, Option<i64>> {
+        self.next.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Next expiration, `None` if the timer is not running.
+    pub(crate) fn expiration(&self) -> Option<i64> {
+        *self.next()
+    }
+
+    /// Call the callback if the timer expired at `now`, and schedule the next
+    /// expiration of periodic timers.
+    pub(crate) fn run(&self, handle: &mut Handle, now: i64) {
+        {
+            let mut next = self.next();
+            match *next {
+                Some(time) if time <= now => {
+                    // A late periodic timer is not run several times to catch up.
+                    *next = self.periodic.then(|| (time + self.interval).max(now));
+                }
+                _ => return,
+            }
+        }
+
+        let mut callback = self.callback.lock().unwrap_or_else(|e| e.into_inner());
+        callback(handle);
+    }
+}
+
+impl Handle {
+    /// Return the internal time in microseconds. This has an arbitrary start
+    /// offset, but will never wrap or go backwards. Timers use this clock.
+    pub fn get_time_us(&mut self) -> i64 {
+        unsafe { mpv_get_time_us(self.as_mut_ptr()) }
+    }
+
+    /// Call `callback` once after `timeout`, like `mp.add_timeout` in Lua.
+    ///
+    /// The callback runs from `Handle::wait_event`, so the event loop must keep
+    /// waiting for events. The timer is cancelled when the returned `Timer` is
+    /// dropped.
+    pub fn add_timeout<F>(&mut self, timeout: Duration, callback: F) -> Timer
+    where
+        F: FnMut(&mut Handle) + Send + 'static,
+    {
+        Timer::new(self, timeout, false, Box::new(callback))
+    }
+
+    /// Call `callback` every `period`, like `mp.add_periodic_timer` in Lua.
+    ///
+    /// See `Handle::add_timeout`. Periods shorter than a millisecond, e.g.
+    /// zero, are rounded up to a millisecond.
+    pub fn add_periodic_timer<F>(&mut self, period: Duration, callback: F) -> Timer
+    where
+        F: FnMut(&mut Handle) + Send + 'static,
+    {
+        Timer::new(self, period.max(MIN_PERIOD), true, Box::new(callback))
+    }
+
+    /// Run the expired timers, and return the next expiration, if any.
+    pub(crate) fn run_timers(&mut self) -> Option<i64> {
+        let handle = unsafe { self.as_ptr() };
+        let now = self.get_time_us();
+        for timer in dispatch::timers(handle) {
+            timer.run(self, now);
+        }
+        dispatch::timers(handle)
+            .iter()
+            .filter_map(|timer| timer.expiration())
+            .min()
+    }
+}
@@ -0,0 +1,212 @@
+use mpv_client::ipc::{IpcClient, IpcError};
+use mpv_client::{Event, Node, Player};
+
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::thread::{self, JoinHandle};
+
+/// `MPV_ERROR_PROPERTY_NOT_FOUND`
+const PROPERTY_NOT_FOUND: i32 = -8;
+
+/// A fake mpv accepting one connection on a socket in the temporary
+/// directory, scripted by `script`.
+struct FakeServer {
+    path: PathBuf,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The accepted connection, as seen by the fake mpv.
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl FakeServer {
+    fn start<F>(name: &str, script: F) -> Self
+    where
+        F: FnOnce(Connection) + Send + 'static,
+    {
+        let path = std::env::temp_dir().join(format!("mpv-client-{}-{}.sock", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let thread = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            script(Connection {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                writer: stream,
+            });
+        });
+        Self {
+            path,
+            thread: Some(thread),
+        }
+    }
+
+    fn connect(&self) -> IpcClient {
+        IpcClient::connect(&self.path).unwrap()
+    }
+
+    /// Wait for the script to finish, failing if it panicked.
+    fn join(mut self) {
+        self.thread.take().unwrap().join().unwrap();
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Connection {
+    /// Read a request, check its command, and return its id.
+    fn expect(&mut self, command: Value) -> Value {
+        let mut line = String::new();
+        self.reader.read_line(&mut line).unwrap();
+        let request: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(request["command"], command);
+        request["request_id"].clone()
+    }
+
+    fn send(&mut self, message: Value) {
+        writeln!(self.writer, "{}", message).unwrap();
+    }
+
+    fn reply(&mut self, request_id: Value, data: Value) {
+        self.send(json!({ "request_id": request_id, "error": "success", "data": data }));
+    }
+}
+
+#[test]
+fn sends_commands() {
+    let server = FakeServer::start("commands", |mut connection| {
+        let id = connection.expect(json!(["loadfile", "video.mkv", "append"]));
+        connection.reply(id, Value::Null);
+        let id = connection.expect(json!({ "name": "expand-text", "text": "${pause}" }));
+        connection.reply(id, json!("no"));
+    });
+    let mut client = server.connect();
+
+    client.command(["loadfile", "video.mkv", "append"]).unwrap();
+    let args = Node::Map(
+        [("name", "expand-text"), ("text", "${pause}")]
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), Node::String(value.to_owned())))
+            .collect(),
+    );
+    assert_eq!(client.command_node(args).unwrap(), Node::String(String::from("no")));
+    server.join();
+}
+
+#[test]
+fn converts_properties() {
+    let server = FakeServer::start("properties", |mut connection| {
+        let id = connection.expect(json!(["get_property", "volume"]));
+        connection.reply(id, json!(75.0));
+        let id = connection.expect(json!(["get_property", "volume"]));
+        connection.reply(id, json!(75.0));
+        let id = connection.expect(json!(["get_property", "pause"]));
+        connection.reply(id, json!(true));
+        let id = connection.expect(json!(["get_property", "playlist-count"]));
+        connection.reply(id, json!(3));
+        let id = connection.expect(json!(["set_property", "speed", 1.5]));
+        connection.reply(id, Value::Null);
+    });
+    let mut client = server.connect();
+
+    assert_eq!(client.get_property::<f64>("volume").unwrap(), 75.0);
+    assert_eq!(client.get_property::<i64>("volume").unwrap(), 75);
+    assert!(client.get_property::<bool>("pause").unwrap());
+    assert_eq!(client.get_property::<String>("playlist-count").unwrap(), "3");
+    client.set_property("speed", 1.5).unwrap();
+    server.join();
+}
+
+#[test]
+fn reports_errors() {
+    let server = FakeServer::start("errors", |mut connection| {
+        let id = connection.expect(json!(["get_property", "nope"]));
+        connection.send(json!({ "request_id": id, "error": "property not found" }));
+    });
+    let mut client = server.connect();
+
+    match client.get_property::<String>("nope") {
+        Err(IpcError::Mpv(e)) => assert_eq!(e.code(), PROPERTY_NOT_FOUND),
+        result => panic!("unexpected result {:?}", result),
+    }
+    server.join();
+}
+
+#[test]
+fn observes_properties() {
+    let server = FakeServer::start("observe", |mut connection| {
+        let id = connection.expect(json!(["observe_property", 7, "pause"]));
+        connection.reply(id, Value::Null);
+        connection.send(json!({ "event": "property-change", "id": 7, "name": "pause", "data": false }));
+        let id = connection.expect(json!(["unobserve_property", 7]));
+        connection.reply(id, Value::Null);
+    });
+    let mut client = server.connect();
+
+    Player::observe_property::<bool>(&mut client, 7, "pause").unwrap();
+    match client.wait_event(5.) {
+        Event::PropertyChange(7, property) => {
+            assert_eq!(property.name(), "pause");
+            assert_eq!(property.data::<bool>(), Some(false));
+        }
+        event => panic!("unexpected event {}", event),
+    }
+    assert_eq!(client.unobserve_property(7).unwrap(), 1);
+    server.join();
+}
+
+#[test]
+fn queues_events_received_during_requests() {
+    let server = FakeServer::start("queue", |mut connection| {
+        let id = connection.expect(json!(["script-message", "hello"]));
+        connection.send(json!({ "event": "client-message", "args": ["hello"] }));
+        connection.send(json!({ "event": "file-loaded" }));
+        connection.reply(id, Value::Null);
+    });
+    let mut client = server.connect();
+
+    client.command(["script-message", "hello"]).unwrap();
+    match client.wait_event(0.) {
+        Event::ClientMessage(message) => assert_eq!(message.args(), ["hello"]),
+        event => panic!("unexpected event {}", event),
+    }
+    assert!(matches!(client.wait_event(0.), Event::FileLoaded));
+    server.join();
+}
+
+#[test]
+fn times_out_without_events() {
+    let server = FakeServer::start("timeout", |connection| {
+        let mut line = String::new();
+        let mut reader = connection.reader;
+        let _ = reader.read_line(&mut line);
+    });
+    let mut client = server.connect();
+
+    assert!(matches!(client.wait_event(0.05), Event::None));
+    drop(client);
+    server.join();
+}
+
+#[test]
+fn shuts_down_when_closed() {
+    let server = FakeServer::start("closed", |mut connection| {
+        connection.send(json!({ "event": "end-file", "reason": "quit", "playlist_entry_id": 1 }));
+    });
+    let mut client = server.connect();
+    server.join();
+
+    match client.wait_event(5.) {
+        Event::EndFile(end_file) => assert_eq!(end_file.playlist_entry_id(), 1),
+        event => panic!("unexpected event {}", event),
+    }
+    assert!(matches!(client.wait_event(5.), Event::Shutdown));
+    assert!(matches!(client.command(["stop"]), Err(IpcError::Closed)));
+}
@@ -0,0 +1,56 @@
+use mpv_client::{Client, Event, Node};
+
+fn client() -> Client {
+    let mut client = Client::new().unwrap();
+    client.set_property("config", String::from("no")).unwrap();
+    client.set_property("idle", String::from("yes")).unwrap();
+    client.initialize().unwrap()
+}
+
+fn options() -> Node {
+    let options = [("a", "1"), ("b", "2")];
+    Node::Map(
+        options
+            .iter()
+            .map(|(key, value)| (key.to_string(), Node::String(value.to_string())))
+            .collect(),
+    )
+}
+
+/// Whether `node` holds the map of `options`.
+fn is_options(node: Option<Node>) -> bool {
+    let Some(Node::Map(map)) = node else {
+        return false;
+    };
+    let value = |key: &str| match map.get(key) {
+        Some(Node::String(value)) => Some(value.as_str()),
+        _ => None,
+    };
+    map.len() == 2 && value("a") == Some("1") && value("b") == Some("2")
+}
+
+#[test]
+fn sets_and_gets_nodes() {
+    let mut client = client();
+    for _ in 0..100 {
+        client.set_property("script-opts", options()).unwrap();
+        assert!(is_options(client.get_property::<Node>("script-opts").ok()));
+    }
+}
+
+#[test]
+fn reads_node_of_event_several_times() {
+    let mut client = client();
+    client.observe_property::<Node>(1, "script-opts").unwrap();
+    client.set_property("script-opts", options()).unwrap();
+    loop {
+        match client.wait_event(5.) {
+            Event::PropertyChange(1, property) if is_options(property.data::<Node>()) => {
+                assert!(is_options(property.data::<Node>()));
+                break;
+            }
+            Event::None | Event::Shutdown => panic!("no change of script-opts"),
+            _ => {}
+        }
+    }
+}
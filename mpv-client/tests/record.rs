@@ -0,0 +1,78 @@
+use mpv_client::mock::MockPlayer;
+use mpv_client::record::{EventRecorder, Replayer};
+use mpv_client::{Event, Player};
+
+use serde_json::{Map, Value};
+
+const RECORDING: &str = r#"
+{"t":0.1,"event":"start-file","playlist_entry_id":1}
+{"t":0.2,"event":"property-change","reply":1,"name":"pause","format":"flag","data":true}
+{"t":0.3,"event":"property-change","reply":2,"name":"volume","format":"double","data":50.0}
+{"t":0.4,"event":"client-message","args":["my-script","toggle"]}
+{"t":0.5,"event":"command-reply","error":null,"reply":3,"result":{"playlist_entry_id":1}}
+{"t":0.6,"event":"log-message","prefix":"cplayer","level":"warn","text":"warning\n"}
+{"t":0.7,"event":"hook","reply":4,"name":"on_load","id":7}
+{"t":0.8,"event":"end-file","reason":"eof","error":null,"playlist_entry_id":1}
+{"t":0.9,"event":"property-change","reply":1,"name":"pause","format":"none","data":null}
+"#;
+
+/// Replay `recording` and record the replayed events again.
+fn rerecord(recording: &str) -> String {
+    let mut replayer = Replayer::new(recording.as_bytes(), MockPlayer::new()).unwrap();
+    let mut recorder = EventRecorder::new(Vec::new());
+    while !matches!(recorder.wait_event(&mut replayer, 0.).unwrap(), Event::Shutdown) {}
+    String::from_utf8(recorder.into_inner()).unwrap()
+}
+
+/// The recorded events, without their timestamps.
+fn events(recording: &str) -> Vec<Map<String, Value>> {
+    recording
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut event: Map<String, Value> = serde_json::from_str(line).unwrap();
+            event.remove("t");
+            event
+        })
+        .collect()
+}
+
+#[test]
+fn replays_recorded_events() {
+    let rerecorded = rerecord(RECORDING);
+    let mut expected = events(RECORDING);
+    // The end of the replay
+    expected.push(Map::from_iter([(String::from("event"), Value::from("shutdown"))]));
+    assert_eq!(events(&rerecorded), expected);
+
+    // The recording of a replay replays the same way.
+    assert_eq!(events(&rerecord(&rerecorded)), events(&rerecorded));
+}
+
+#[test]
+fn replays_properties() {
+    let mut replayer = Replayer::new(RECORDING.as_bytes(), MockPlayer::new()).unwrap();
+    for _ in 0..3 {
+        replayer.wait_event(0.);
+    }
+    assert!(replayer.get_property::<bool>("pause").unwrap());
+    assert_eq!(replayer.get_property::<f64>("volume").unwrap(), 50.);
+
+    while !matches!(replayer.wait_event(0.), Event::Shutdown) {}
+    assert!(replayer.get_property::<bool>("pause").is_err());
+    assert_eq!(replayer.remaining(), 0);
+}
+
+#[test]
+fn replays_only_recorded_events() {
+    let mut replayer = Replayer::new(RECORDING.as_bytes(), MockPlayer::new()).unwrap();
+    // The mock sends the current value of observed properties, which the
+    // replayer discards.
+    replayer.observe_property::<bool>(1, "pause").unwrap();
+    assert!(matches!(replayer.wait_event(0.), Event::StartFile(_)));
+    replayer.set_property("volume", 80.).unwrap();
+    replayer.observe_property::<f64>(2, "volume").unwrap();
+    assert!(matches!(replayer.wait_event(0.), Event::PropertyChange(1, _)));
+    assert!(matches!(replayer.wait_event(0.), Event::PropertyChange(2, _)));
+    assert!(matches!(replayer.wait_event(0.), Event::ClientMessage(_)));
+}